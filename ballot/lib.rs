@@ -2,14 +2,44 @@
 
 use ink_lang as ink;
 
+pub mod merkle;
+
 #[ink::contract]
 mod ballot {
 
     use ink_prelude::{
         string::String,
+        vec,
         vec::Vec,
     };
+    use ink_env::call::{
+        build_call,
+        Call,
+        ExecutionInput,
+        FromAccountId,
+        Selector,
+    };
     use ink_storage::{Mapping, traits::{SpreadAllocate, PackedLayout, SpreadLayout}};
+    use crate::merkle;
+    use housetoken::{
+        HouseId,
+        HousetokenRef,
+    };
+
+    /// Identifies a single ballot hosted by the contract.
+    pub type BallotId = u32;
+
+    /// Groups proposals by topic for scoped delegation,
+    /// proposals start out in category `0`.
+    pub type Category = u32;
+
+    /// The share of a voter's weight that stands for all of it,
+    /// delegation shares are given in parts of this.
+    pub const FULL_SHARE: u32 = 10_000;
+
+    /// The highest conviction a vote can be cast with,
+    /// multiplying its weight by `2^MAX_CONVICTION`.
+    pub const MAX_CONVICTION: u32 = 6;
 
     /// This declares a new complex type which will
     /// be used for vairables later.
@@ -19,8 +49,44 @@ mod ballot {
     pub struct Voter {
         weight: u64,
         voted: bool,
-        delegate: AccountId,
-        vote: i32
+        // the delegates together with their share of the
+        // voter's weight, in parts of `FULL_SHARE`
+        delegations: Vec<(AccountId, u32)>,
+        vote: i32,
+        // proposal indices in order of preference on
+        // ranked ballots, the approved proposals on
+        // approval ballots
+        choices: Vec<u32>,
+        // credits left to spend and the votes bought
+        // per proposal, only used by quadratic ballots
+        credits: u64,
+        allocation: Vec<(u32, u64)>,
+        // hash of the vote to be revealed,
+        // only used by secret ballots
+        commitment: Option<[u8; 32]>,
+        // set instead of a vote when abstaining
+        abstention: Option<Abstention>,
+        // the category delegate whose vote was cast for
+        // this voter, the vote moves along with theirs
+        following: Option<AccountId>,
+        // the part of `weight` delegated by others,
+        // the rest is the voter's own
+        delegated_weight: u64,
+        // the voter's own weight counts 2^conviction times
+        // on a conviction vote, the extra votes are kept as
+        // bonus, in return for the stake locked until
+        // `lock_expiry`
+        conviction: u32,
+        conviction_bonus: u64,
+        locked_balance: Balance,
+        lock_expiry: Timestamp,
+    }
+
+    impl Voter {
+        /// the votes counted for this voter's vote
+        fn counted_weight(&self) -> u64 {
+            self.weight + self.conviction_bonus
+        }
     }
 
     // This is a type of single proposal.
@@ -29,6 +95,18 @@ mod ballot {
     pub struct Proposal {
         name: String,
         vote_count: u64,
+        category: Category,
+        // the call to dispatch if this proposal wins
+        call: Option<ProposalCall>,
+        // who submitted the proposal during the proposal
+        // phase, with the hash of its full description
+        // and the deposit they are owed back
+        proposer: Option<AccountId>,
+        description_hash: [u8; 32],
+        deposit: Balance,
+        // set once the chairperson rejected the proposal,
+        // it cannot be voted for
+        rejected: bool,
     }
 
     impl ink_storage::traits::PackedAllocate for Proposal {
@@ -37,141 +115,3241 @@ mod ballot {
         }
     }
 
+    /// A call to another contract, dispatched by
+    /// `execute_winner` once its proposal won.
+    #[derive(scale::Decode, scale::Encode, Debug, PackedLayout, SpreadLayout, Clone)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct ProposalCall {
+        pub callee: AccountId,
+        pub selector: [u8; 4],
+        /// The SCALE encoded arguments of the message.
+        pub input: Vec<u8>,
+        pub transferred_value: Balance,
+    }
+
+    /// A ranking together with the total weight of
+    /// all ballots that ranked proposals this way.
+    #[derive(scale::Decode, scale::Encode, Default, Debug, PackedLayout, SpreadLayout, SpreadAllocate, Clone)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct RankedBallot {
+        ranking: Vec<u32>,
+        weight: u64,
+    }
+
+    impl ink_storage::traits::PackedAllocate for RankedBallot {
+        fn allocate_packed(&mut self, _at: &ink_primitives::Key) {}
+    }
+
+    /// How voters express their choice on a ballot.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode, PackedLayout, SpreadLayout)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum VotingMode {
+        /// Every voter picks a single proposal.
+        Plurality,
+        /// Every voter ranks proposals in order of preference,
+        /// the winner is found by instant-runoff.
+        Ranked,
+        /// Every voter spreads votes over proposals,
+        /// casting `n` votes on a proposal costs `n * n` credits.
+        Quadratic,
+        /// Every voter approves any number of proposals,
+        /// each of them receives the voter's full weight.
+        Approval,
+        /// Every voter commits to a single proposal while
+        /// voting is open and reveals it afterwards,
+        /// unrevealed votes are not counted.
+        Secret,
+    }
+
+    /// The ways to take part in a ballot without
+    /// supporting any proposal.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode, PackedLayout, SpreadLayout)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum Abstention {
+        /// Count towards the quorum only.
+        Abstain,
+        /// Vote against every proposal, "none of the above".
+        RejectAll,
+    }
+
+    /// Where voters get their weight from.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode, PackedLayout, SpreadLayout)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum WeightSource {
+        /// The chairperson gives voters the right to vote,
+        /// directly or through a Merkle allowlist.
+        Chairperson,
+        /// Voters hold the balance of a PSP22 token at
+        /// `snapshot_block` as weight, looked up through
+        /// its `PSP22Snapshot` extension on their first
+        /// vote or delegation. Every `weight_unit` of the
+        /// balance counts as one vote, rounding down.
+        /// The snapshot has to be taken before the ballot
+        /// is created.
+        Token {
+            token: AccountId,
+            snapshot_block: BlockNumber,
+            weight_unit: Balance,
+        },
+        /// Every house owned on the `housetoken` contract
+        /// grants one vote, looked up on the owner's first
+        /// vote or delegation. Each house counts once per
+        /// ballot, whoever owns it later.
+        Houses {
+            housetoken: AccountId,
+        },
+    }
+
+    /// The settings a ballot is created with. Times are
+    /// in milliseconds, like the block timestamp.
+    #[derive(Debug, Clone, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct BallotConfig {
+        /// How voters express their choice.
+        pub mode: VotingMode,
+        /// Where voters get their weight from.
+        pub weight_source: WeightSource,
+        /// When voters can no longer be registered.
+        pub registration_end: Timestamp,
+        /// When registered voters can no longer submit proposals,
+        /// the proposal phase starts at `registration_end`.
+        pub proposal_end: Timestamp,
+        /// The most proposals the ballot can have.
+        pub max_proposals: u32,
+        /// Deposit to transfer with a submitted proposal,
+        /// paid back unless the proposal is rejected. A
+        /// forfeited deposit stays with the contract, to be
+        /// spent by the calls of winning proposals.
+        pub proposal_deposit: Balance,
+        /// When voting closes, it opens at `proposal_end`.
+        pub voting_end: Timestamp,
+        /// Credits every voter gets on quadratic ballots.
+        pub credit_budget: u64,
+        /// When secret ballots stop taking reveals, they start
+        /// at `voting_end`. Ignored by the other modes.
+        pub reveal_end: Timestamp,
+        /// The most delegations a vote can pass through before
+        /// reaching the voter casting it, `0` disables delegation.
+        pub max_delegation_depth: u32,
+        /// The least total weight that has to be cast
+        /// for the ballot to be decided.
+        pub quorum: u64,
+        /// The share of the counted votes, in percent,
+        /// the leading proposal needs to pass.
+        pub threshold_percent: u32,
+        /// Milliseconds to wait after closing the ballot before
+        /// the winner's call can be executed, guardians can
+        /// veto it in the meantime.
+        pub execution_delay: Timestamp,
+        /// The accounts allowed to veto the winner's call.
+        pub guardians: Vec<AccountId>,
+        /// Milliseconds stake stays locked after voting ended for
+        /// a conviction of `0`, doubling with each step.
+        pub conviction_period: Timestamp,
+        /// Stake to lock per unit of weight on a conviction vote.
+        pub conviction_stake: Balance,
+    }
+
+    /// A voter's claim to be part of the ballot's
+    /// Merkle allowlist with the given weight.
+    #[derive(Debug, Clone, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct WeightProof {
+        pub weight: u64,
+        /// The sibling nodes from the leaf up to the root.
+        pub proof: Vec<merkle::Node>,
+    }
+
+    /// A plurality vote signed off-chain by the voter,
+    /// to be relayed with `vote_by_signature`.
+    #[derive(Debug, Clone, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct SignedVote {
+        pub ballot_id: BallotId,
+        pub proposal: i32,
+        /// Has to match the voter's current `nonce`.
+        pub nonce: u64,
+        /// The vote cannot be relayed after this time.
+        pub deadline: Timestamp,
+    }
+
+    /// A single ballot hosted by the contract.
+    #[derive(scale::Decode, scale::Encode, Debug, Clone, PackedLayout, SpreadLayout)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct BallotInfo {
+        chair_person: AccountId,
+        // A dynamically-sized array of `Proposal` structs.
+        proposals: Vec<Proposal>,
+        mode: VotingMode,
+        weight_source: WeightSource,
+        // credits every voter gets on quadratic ballots
+        credit_budget: u64,
+        // limits on submitted proposals
+        max_proposals: u32,
+        proposal_deposit: Balance,
+        // end of the registration, proposal, voting
+        // and reveal phase
+        registration_end: Timestamp,
+        proposal_end: Timestamp,
+        voting_end: Timestamp,
+        reveal_end: Timestamp,
+        // the most delegations a vote can pass through
+        max_delegation_depth: u32,
+        // least weight to be cast and share of the
+        // counted votes in percent needed to pass
+        quorum: u64,
+        threshold_percent: u32,
+        // total weight of the votes counted so far,
+        // including abstentions and rejections
+        turnout: u64,
+        // weight of the voters that abstained
+        // or rejected every proposal
+        abstentions: u64,
+        rejections: u64,
+        // number of votes committed and revealed
+        // on secret ballots
+        commitments: u32,
+        reveals: u32,
+        // Merkle root of the `(account, weight)` pairs
+        // allowed to claim their right to vote
+        voter_root: Option<merkle::Node>,
+        // set to true once the chairperson closed the ballot,
+        // disallows any further change.
+        closed: bool,
+        closed_at: Timestamp,
+        // milliseconds between closing and executing the winner's
+        // call, and whether the call succeeded once executed
+        execution_delay: Timestamp,
+        execution: Option<bool>,
+        // who can veto the winner's call and, once they
+        // did, the guardian together with their reason
+        guardians: Vec<AccountId>,
+        veto: Option<(AccountId, String)>,
+        // lock period and stake per unit of weight
+        // of conviction votes
+        conviction_period: Timestamp,
+        conviction_stake: Balance,
+    }
+
+    impl BallotInfo {
+        /// the phase the ballot is in at time `now`
+        fn phase(&self, now: Timestamp) -> Phase {
+            if self.closed {
+                Phase::Closed
+            } else if now < self.registration_end {
+                Phase::Registration
+            } else if now < self.proposal_end {
+                Phase::Proposal
+            } else if now < self.voting_end {
+                Phase::Voting
+            } else if now < self.reveal_end {
+                Phase::Reveal
+            } else {
+                Phase::Tally
+            }
+        }
+
+        /// make sure `proposal` exists and was not rejected
+        fn check_proposal(&self, proposal: i32) -> Result<()> {
+            let proposal = usize::try_from(proposal)
+                .ok()
+                .and_then(|p| self.proposals.get(p))
+                .ok_or(BallotError::ProposalOutOfRange)?;
+            if proposal.rejected {
+                return Err(BallotError::ProposalRejected)
+            }
+            Ok(())
+        }
+    }
+
+    /// The reasons a ballot message can fail.
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum BallotError {
+        /// There is no ballot with the given id.
+        BallotNotFound,
+        /// The caller is not the chairperson of the ballot.
+        NotChairperson,
+        /// The voter already voted or delegated their vote.
+        AlreadyVoted,
+        /// The voter was already given the right to vote.
+        AlreadyHasRightToVote,
+        /// The voter has no right to vote on this ballot.
+        NoRightToVote,
+        /// The delegate has no right to vote on this ballot.
+        DelegateHasNoRightToVote,
+        /// Voters cannot delegate to themselves.
+        SelfDelegation,
+        /// The delegation would end up back at the caller.
+        DelegationLoop,
+        /// There is no proposal with the given index.
+        ProposalOutOfRange,
+        /// The message cannot be called in the current phase.
+        WrongPhase,
+        /// The message is not supported by the voting mode of the ballot.
+        WrongMode,
+        /// The same proposal was given more than once.
+        DuplicateProposal,
+        /// No proposal was given.
+        NoProposalSelected,
+        /// The voter does not have enough credits left.
+        NotEnoughCredits,
+        /// The chairperson did not commit a voter allowlist.
+        NoVoterRoot,
+        /// The proof does not match the voter allowlist.
+        InvalidProof,
+        /// Voters cannot be given a weight of zero.
+        ZeroWeight,
+        /// The voter already committed to a vote.
+        AlreadyCommitted,
+        /// The voter did not commit to a vote.
+        NoCommitment,
+        /// The revealed vote does not match the commitment.
+        CommitmentMismatch,
+        /// The voter did not vote.
+        NotVoted,
+        /// The voter delegated their vote instead of voting.
+        HasDelegated,
+        /// The voter did not delegate their vote.
+        NotDelegated,
+        /// The delegation shares do not add up to `FULL_SHARE`.
+        InvalidShares,
+        /// The same delegate was given more than once.
+        DuplicateDelegate,
+        /// The execution delay after closing the ballot
+        /// did not pass yet.
+        TimelockActive,
+        /// No proposal passed.
+        NotPassed,
+        /// The winning proposal carries no call.
+        NoProposalCall,
+        /// The winner's call was executed already.
+        AlreadyExecuted,
+        /// Voters get their weight elsewhere on this ballot.
+        WrongWeightSource,
+        /// The token contract could not be queried.
+        TokenQueryFailed,
+        /// The token snapshot is not taken before the ballot.
+        SnapshotNotInPast,
+        /// The phase end times are out of order or already passed.
+        InvalidSchedule,
+        /// Token balances cannot be counted in units of zero.
+        ZeroWeightUnit,
+        /// The weight is too large to be counted.
+        WeightOverflow,
+        /// The conviction is above `MAX_CONVICTION`.
+        ConvictionTooHigh,
+        /// Less stake was transferred than the vote's
+        /// weight requires.
+        NotEnoughStake,
+        /// The voter has no stake locked.
+        NothingLocked,
+        /// The voter's stake is still locked.
+        StillLocked,
+        /// Sending the stake back failed.
+        TransferFailed,
+        /// The contract cannot pay the call's value without
+        /// touching locked stake or proposal deposits.
+        InsufficientFunds,
+        /// The signature does not belong to any voter.
+        InvalidSignature,
+        /// The signed vote was relayed after its deadline.
+        SignatureExpired,
+        /// The signed vote does not carry the voter's nonce.
+        InvalidNonce,
+        /// The chairperson rejected the proposal.
+        ProposalRejected,
+        /// The ballot has as many proposals as it can have.
+        TooManyProposals,
+        /// The caller did not submit the proposal.
+        NotProposer,
+        /// Less was transferred than the proposal deposit.
+        NotEnoughDeposit,
+        /// There is no deposit to pay back.
+        NoDeposit,
+        /// The caller is not a guardian of the ballot.
+        NotGuardian,
+        /// The winner's call was vetoed by a guardian.
+        Vetoed,
+        /// The execution delay is over, the winner's
+        /// call cannot be vetoed anymore.
+        TimelockExpired,
+        /// The delegation would make a chain longer than
+        /// the ballot allows.
+        DelegationTooDeep,
+    }
+
+    /// The outcome of a ballot under its quorum
+    /// and threshold rules.
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum BallotResult {
+        /// The proposal with the given index won.
+        Passed(u32),
+        /// Less weight was cast than the quorum requires.
+        NoQuorum,
+        /// The proposals with the given indices share
+        /// the highest vote count.
+        Tie(Vec<u32>),
+        /// The leading proposal fell short of the threshold.
+        BelowThreshold,
+        /// At least as much weight rejected every proposal
+        /// as the leading proposal received.
+        Rejected,
+    }
+
+    /// The counts behind a ballot's result.
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct Tally {
+        /// The vote count of every proposal, the last
+        /// runoff round on ranked ballots.
+        pub vote_counts: Vec<u64>,
+        pub abstentions: u64,
+        pub rejections: u64,
+        pub turnout: u64,
+    }
+
+    /// Where the winner's call stands after the ballot.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum ExecutionState {
+        /// The ballot was not closed yet.
+        Pending,
+        /// No proposal passed or the winner carries no call.
+        NoAction,
+        /// The call waits for the execution delay to pass,
+        /// guardians can still veto it until then.
+        Queued,
+        /// A guardian vetoed the call.
+        Vetoed,
+        /// The call was dispatched and succeeded.
+        Executed,
+        /// The call was dispatched and failed.
+        Failed,
+    }
+
+    /// The phases a ballot goes through, in order.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum Phase {
+        /// The chairperson gives voters the right to vote.
+        Registration,
+        /// Voters submit proposals.
+        Proposal,
+        /// Voters vote or delegate their vote.
+        Voting,
+        /// Voters reveal the votes committed on a secret ballot.
+        Reveal,
+        /// Voting ended, the ballot can be closed.
+        Tally,
+        /// The ballot was closed, the winner is final.
+        Closed,
+    }
+
+    /// The ballot result type.
+    pub type Result<T> = core::result::Result<T, BallotError>;
+
     /// Defines the storage of your contract.
     /// Add new fields to the below struct in order
     /// to add new static storage fields to your contract.
     #[derive(SpreadAllocate)]
     #[ink(storage)]
     pub struct Ballot {
-        chair_person: AccountId,
+        // every ballot hosted by this contract
+        ballots: Mapping<BallotId, BallotInfo>,
+        // number of ballots created so far,
+        // which is also the id of the next one
+        ballot_count: BallotId,
         // This declares a state variable that
         // Stores a `Voter` struct for each possiable address
-        voters: Mapping<AccountId, Voter>,
-        // A dynamically-sized array of `Proposal` structs.
-        proposals: Vec<Proposal>,
+        // on each ballot
+        voters: Mapping<(BallotId, AccountId), Voter>,
+        // every distinct ranking cast so far on each ballot,
+        // kept one per cell so a vote only touches its own
+        ranked_ballots: Mapping<(BallotId, u32), RankedBallot>,
+        ranked_ballot_ids: Mapping<(BallotId, Vec<u32>), u32>,
+        ranked_ballot_count: Mapping<BallotId, u32>,
+        // the delegate a voter chose for each category
+        // and, the other way round, who chose a delegate
+        category_delegates: Mapping<(BallotId, AccountId, Category), AccountId>,
+        category_delegators: Mapping<(BallotId, AccountId, Category), Vec<AccountId>>,
+        // everyone who delegated (part of) their vote to a voter
+        delegators: Mapping<(BallotId, AccountId), Vec<AccountId>>,
+        // the voter each house was counted for
+        house_voters: Mapping<(BallotId, HouseId), AccountId>,
+        // number of signed votes relayed for each voter
+        nonces: Mapping<AccountId, u64>,
+        // stake locked by conviction votes and proposal
+        // deposits, owed back and not to be spent by calls
+        reserved: Balance,
+    }
+
+    // events that will be emitted on changes
+    #[ink(event)]
+    pub struct BallotCreated {
+        #[ink(topic)]
+        ballot_id: BallotId,
+        #[ink(topic)]
+        chair_person: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct ProposalSubmitted {
+        #[ink(topic)]
+        ballot_id: BallotId,
+        #[ink(topic)]
+        proposer: AccountId,
+        proposal: u32,
+    }
+
+    #[ink(event)]
+    pub struct ProposalRejected {
+        #[ink(topic)]
+        ballot_id: BallotId,
+        proposal: u32,
+    }
+
+    #[ink(event)]
+    pub struct RightToVoteGranted {
+        #[ink(topic)]
+        ballot_id: BallotId,
+        #[ink(topic)]
+        voter: AccountId,
+        weight: u64,
+    }
+
+    #[ink(event)]
+    pub struct Delegated {
+        #[ink(topic)]
+        ballot_id: BallotId,
+        #[ink(topic)]
+        from: AccountId,
+        #[ink(topic)]
+        to: AccountId,
+        weight: u64,
+    }
+
+    #[ink(event)]
+    pub struct CategoryDelegated {
+        #[ink(topic)]
+        ballot_id: BallotId,
+        #[ink(topic)]
+        from: AccountId,
+        #[ink(topic)]
+        to: AccountId,
+        category: Category,
+    }
+
+    #[ink(event)]
+    pub struct Undelegated {
+        #[ink(topic)]
+        ballot_id: BallotId,
+        #[ink(topic)]
+        from: AccountId,
+        #[ink(topic)]
+        to: AccountId,
+        weight: u64,
+    }
+
+    #[ink(event)]
+    pub struct Voted {
+        #[ink(topic)]
+        ballot_id: BallotId,
+        #[ink(topic)]
+        voter: AccountId,
+        proposal: i32,
+        weight: u64,
+    }
+
+    #[ink(event)]
+    pub struct Abstained {
+        #[ink(topic)]
+        ballot_id: BallotId,
+        #[ink(topic)]
+        voter: AccountId,
+        kind: Abstention,
+        weight: u64,
+    }
+
+    #[ink(event)]
+    pub struct Unlocked {
+        #[ink(topic)]
+        ballot_id: BallotId,
+        #[ink(topic)]
+        voter: AccountId,
+        amount: Balance,
+    }
+
+    #[ink(event)]
+    pub struct VoteRevoked {
+        #[ink(topic)]
+        ballot_id: BallotId,
+        #[ink(topic)]
+        voter: AccountId,
+        weight: u64,
+    }
+
+    #[ink(event)]
+    pub struct VoteCommitted {
+        #[ink(topic)]
+        ballot_id: BallotId,
+        #[ink(topic)]
+        voter: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct BallotClosed {
+        #[ink(topic)]
+        ballot_id: BallotId,
+        result: BallotResult,
+    }
+
+    #[ink(event)]
+    pub struct ProposalVetoed {
+        #[ink(topic)]
+        ballot_id: BallotId,
+        #[ink(topic)]
+        guardian: AccountId,
+        reason: String,
+    }
+
+    #[ink(event)]
+    pub struct ProposalExecuted {
+        #[ink(topic)]
+        ballot_id: BallotId,
+        proposal: u32,
+        success: bool,
     }
 
     impl Ballot {
-        /// Constructor that initializes the `bool` value to the given `init_value`.
+        /// create the contract together with a first
+        /// ballot on the given proposals, chaired by the caller.
         #[ink(constructor)]
-        pub fn new(proposal_name: Vec<String>) -> Self {
+        pub fn new(proposal_name: Vec<String>, config: BallotConfig) -> Self {
             let caller = Self::env().caller();
             ink_lang::utils::initialize_contract(|contract: &mut Ballot|{
-                contract.chair_person = caller;
-                let mut voter = contract.voters.get(caller).unwrap_or_default();
-                voter.weight = 1;
-
-                // For each of the provided proposal names,
-                // create a new proposal object and add it
-                // to the end of the array
-                let i = 0;
-                for i in i..=proposal_name.len(){
-                    let proposal = Proposal {
-                        name: proposal_name[i].clone(),
-                        vote_count: 0,
-                    };
-                    contract.proposals.push(proposal);
-                }
+                contract
+                    .add_ballot(caller, proposal_name, config)
+                    .expect("invalid ballot config");
             })
         }
 
+        /// create the contract without any ballot,
+        /// ballots are added with `create_ballot`.
+        #[ink(constructor)]
+        pub fn new_registry() -> Self {
+            ink_lang::utils::initialize_contract(|_contract: &mut Ballot|{})
+        }
+
+        /// create a new ballot on the given proposals,
+        /// chaired by the caller.
+        #[ink(message)]
+        pub fn create_ballot(&mut self, proposal_name: Vec<String>, config: BallotConfig) -> Result<BallotId> {
+            let caller = self.env().caller();
+            self.add_ballot(caller, proposal_name, config)
+        }
+
+        /// number of ballots created so far.
+        /// Ballot ids run from `0` up to this number.
+        #[ink(message)]
+        pub fn ballot_count(&self) -> BallotId {
+            self.ballot_count
+        }
+
+        /// up to `limit` ballots, starting at id `from`
+        #[ink(message)]
+        pub fn list_ballots(&self, from: BallotId, limit: u32) -> Vec<(BallotId, BallotInfo)> {
+            let to = self.ballot_count.min(from.saturating_add(limit));
+            (from..to)
+                .filter_map(|id| self.ballots.get(id).map(|ballot| (id, ballot)))
+                .collect()
+        }
+
+        #[ink(message)]
+        pub fn get_ballot(&self, ballot_id: BallotId) -> Option<BallotInfo> {
+            self.ballots.get(ballot_id)
+        }
+
+        #[ink(message)]
+        pub fn get_voter(&self, ballot_id: BallotId, voter: AccountId) -> Option<Voter> {
+            self.voters.get((ballot_id, voter))
+        }
+
+        /// the voter `house` was counted for on a
+        /// house-weighted ballot, if any
+        #[ink(message)]
+        pub fn house_voter(&self, ballot_id: BallotId, house: HouseId) -> Option<AccountId> {
+            self.house_voters.get((ballot_id, house))
+        }
+
         // Give `voter` the right to vote on this ballot.
         // May only be called by `chairperson`
         #[ink(message)]
-        pub fn give_right_to_vote(&mut self, voter: AccountId) {
+        pub fn give_right_to_vote(&mut self, ballot_id: BallotId, voter: AccountId) -> Result<()> {
             let caller = self.env().caller();
-            assert!(caller == self.chair_person, "Only chairperson can give right to vote");
+            let ballot = self.ballot(ballot_id)?;
+            if caller != ballot.chair_person {
+                return Err(BallotError::NotChairperson)
+            }
+            if ballot.phase(self.env().block_timestamp()) != Phase::Registration {
+                return Err(BallotError::WrongPhase)
+            }
+            if ballot.weight_source != WeightSource::Chairperson {
+                return Err(BallotError::WrongWeightSource)
+            }
 
-            let mut voters = self.voters.get(voter).unwrap_or_default();
-            assert!(!voters.voted, "The voter already voted");
-            assert!(voters.weight == 0);
-            voters.weight = 1;
+            self.register_voter(ballot_id, &ballot, voter, 1)
         }
 
+        /// Give every listed voter the right to vote with
+        /// the paired weight, all in a single call.
+        /// Entries are registered one by one: an entry that
+        /// cannot be registered is skipped without stopping
+        /// the others, and returned together with the reason.
+        /// The whole batch fails only if the call itself is
+        /// not allowed.
+        /// May only be called by `chairperson`
+        #[ink(message)]
+        pub fn give_rights_to_vote(&mut self, ballot_id: BallotId, voters: Vec<(AccountId, u64)>) -> Result<Vec<(AccountId, BallotError)>> {
+            let caller = self.env().caller();
+            let ballot = self.ballot(ballot_id)?;
+            if caller != ballot.chair_person {
+                return Err(BallotError::NotChairperson)
+            }
+            if ballot.phase(self.env().block_timestamp()) != Phase::Registration {
+                return Err(BallotError::WrongPhase)
+            }
+            if ballot.weight_source != WeightSource::Chairperson {
+                return Err(BallotError::WrongWeightSource)
+            }
+
+            let mut rejected = Vec::new();
+            for (voter, weight) in voters {
+                if let Err(error) = self.register_voter(ballot_id, &ballot, voter, weight) {
+                    rejected.push((voter, error));
+                }
+            }
+            Ok(rejected)
+        }
 
-        // Delegeate your vote to the voter `to`
 
-        #[ink(message)] 
-        pub fn delegate(&mut self, to: AccountId) {
+        /// Put `proposal` in `category`, so voters can
+        /// delegate all proposals of a topic at once.
+        /// May only be called by `chairperson`
+        #[ink(message)]
+        pub fn set_proposal_category(&mut self, ballot_id: BallotId, proposal: u32, category: Category) -> Result<()> {
             let caller = self.env().caller();
-            let mut voters = self.voters.get(caller).unwrap_or_default();
-            let mut voters_to = self.voters.get(to).unwrap_or_default();
-            assert!(!voters.voted, "You already voted.");
+            let mut ballot = self.ballot(ballot_id)?;
+            if caller != ballot.chair_person {
+                return Err(BallotError::NotChairperson)
+            }
+            if !matches!(ballot.phase(self.env().block_timestamp()), Phase::Registration | Phase::Proposal) {
+                return Err(BallotError::WrongPhase)
+            }
 
-            assert!(to != caller, "Self-delegation is disallowed");
+            ballot
+                .proposals
+                .get_mut(proposal as usize)
+                .ok_or(BallotError::ProposalOutOfRange)?
+                .category = category;
+            self.ballots.insert(ballot_id, &ballot);
+            Ok(())
+        }
 
-            // Forward the delegation as long as 
-            // `to` also delegated
-            // In general, such loops are very dangerous,
-            // because if they run too long, they might need more gas than is available in block.
-            // In this case, the delegation will not be execuated,
-            // but in other situations, such loops might
-            // cause a contract to get "stuck" completely
+        /// Submit a proposal during the proposal phase,
+        /// together with the hash of its full description.
+        /// The transferred value, at least the ballot's
+        /// `proposal_deposit`, is paid back with
+        /// `reclaim_deposit` unless the proposal is rejected.
+        #[ink(message, payable)]
+        pub fn submit_proposal(&mut self, ballot_id: BallotId, name: String, description_hash: [u8; 32]) -> Result<u32> {
+            let caller = self.env().caller();
+            let mut ballot = self.ballot(ballot_id)?;
+            if ballot.phase(self.env().block_timestamp()) != Phase::Proposal {
+                return Err(BallotError::WrongPhase)
+            }
+            if self.load_voter(ballot_id, &ballot, caller)?.weight == 0 {
+                return Err(BallotError::NoRightToVote)
+            }
+            if ballot.proposals.len() >= ballot.max_proposals as usize {
+                return Err(BallotError::TooManyProposals)
+            }
+            let deposit = self.env().transferred_value();
+            if deposit < ballot.proposal_deposit {
+                return Err(BallotError::NotEnoughDeposit)
+            }
 
-            while voters_to.delegate != [0; 32].into() {
-                voters_to.delegate = to;
+            self.reserved += deposit;
+            let proposal = ballot.proposals.len() as u32;
+            ballot.proposals.push(Proposal {
+                name,
+                proposer: Some(caller),
+                description_hash,
+                deposit,
+                ..Default::default()
+            });
+            self.ballots.insert(ballot_id, &ballot);
+            self.env().emit_event(ProposalSubmitted{
+                ballot_id,
+                proposer: caller,
+                proposal,
+            });
+            Ok(proposal)
+        }
 
-                // We found a loop in the delegation, not allowed.
-                assert!(to != caller, "Found loop in delegation");
+        /// Reject `proposal` as spam, it cannot be voted for
+        /// and its deposit stays with the contract.
+        /// May only be called by `chairperson`
+        #[ink(message)]
+        pub fn reject_proposal(&mut self, ballot_id: BallotId, proposal: u32) -> Result<()> {
+            let caller = self.env().caller();
+            let mut ballot = self.ballot(ballot_id)?;
+            if caller != ballot.chair_person {
+                return Err(BallotError::NotChairperson)
+            }
+            if !matches!(ballot.phase(self.env().block_timestamp()), Phase::Registration | Phase::Proposal) {
+                return Err(BallotError::WrongPhase)
             }
+            ballot.check_proposal(proposal as i32)?;
 
-            // Since `sender` is a reference, this
-            // modifies `voters.voted`
-            voters.voted = true;
-            voters.delegate = to;
+            let rejected = &mut ballot.proposals[proposal as usize];
+            rejected.rejected = true;
+            // the chairperson gains nothing from rejecting,
+            // the deposit is just no longer owed back
+            let deposit = core::mem::take(&mut rejected.deposit);
+            self.ballots.insert(ballot_id, &ballot);
+            self.reserved -= deposit;
+            self.env().emit_event(ProposalRejected{
+                ballot_id,
+                proposal,
+            });
+            Ok(())
+        }
 
-            if voters_to.voted {
-                // If the delegate already voted,
-                // directly add to the number of votes
-                let mut _vote_count = self.proposals[voters_to.vote as usize].vote_count;
-                _vote_count += voters.weight;
-            } else {
-                voters_to.weight += voters.weight;
+        /// Take back the deposit of a proposal you submitted
+        /// once voting ended.
+        #[ink(message)]
+        pub fn reclaim_deposit(&mut self, ballot_id: BallotId, proposal: u32) -> Result<()> {
+            let caller = self.env().caller();
+            let mut ballot = self.ballot(ballot_id)?;
+            if !matches!(ballot.phase(self.env().block_timestamp()), Phase::Tally | Phase::Closed) {
+                return Err(BallotError::WrongPhase)
+            }
+            let submitted = ballot
+                .proposals
+                .get_mut(proposal as usize)
+                .ok_or(BallotError::ProposalOutOfRange)?;
+            if submitted.proposer != Some(caller) {
+                return Err(BallotError::NotProposer)
+            }
+            if submitted.deposit == 0 {
+                return Err(BallotError::NoDeposit)
             }
 
+            let deposit = core::mem::take(&mut submitted.deposit);
+            self.ballots.insert(ballot_id, &ballot);
+            self.reserved -= deposit;
+            self.env().transfer(caller, deposit).map_err(|_| BallotError::TransferFailed)
         }
 
-        // Give your vote (including votes delegated to you)
-        // to proposal `proposals[proposal].name`
+        /// Attach the call `proposal` dispatches when it
+        /// wins, or remove it with `None`.
+        /// May only be called by `chairperson`
+        #[ink(message)]
+        pub fn set_proposal_call(&mut self, ballot_id: BallotId, proposal: u32, call: Option<ProposalCall>) -> Result<()> {
+            let caller = self.env().caller();
+            let mut ballot = self.ballot(ballot_id)?;
+            if caller != ballot.chair_person {
+                return Err(BallotError::NotChairperson)
+            }
+            if !matches!(ballot.phase(self.env().block_timestamp()), Phase::Registration | Phase::Proposal) {
+                return Err(BallotError::WrongPhase)
+            }
+
+            ballot
+                .proposals
+                .get_mut(proposal as usize)
+                .ok_or(BallotError::ProposalOutOfRange)?
+                .call = call;
+            self.ballots.insert(ballot_id, &ballot);
+            Ok(())
+        }
+
+        /// Commit the Merkle root of the `(account, weight)`
+        /// pairs allowed to vote, see the `merkle` module.
+        /// Listed voters claim their weight with a proof.
+        /// May only be called by `chairperson`
+        #[ink(message)]
+        pub fn set_voter_root(&mut self, ballot_id: BallotId, root: merkle::Node) -> Result<()> {
+            let caller = self.env().caller();
+            let mut ballot = self.ballot(ballot_id)?;
+            if caller != ballot.chair_person {
+                return Err(BallotError::NotChairperson)
+            }
+            if ballot.phase(self.env().block_timestamp()) != Phase::Registration {
+                return Err(BallotError::WrongPhase)
+            }
+            if ballot.weight_source != WeightSource::Chairperson {
+                return Err(BallotError::WrongWeightSource)
+            }
+
+            ballot.voter_root = Some(root);
+            self.ballots.insert(ballot_id, &ballot);
+            Ok(())
+        }
+
+        /// Claim the right to vote granted to the caller by
+        /// the ballot's Merkle allowlist. `vote` and `delegate`
+        /// accept the proof directly.
+        #[ink(message)]
+        pub fn claim_right_to_vote(&mut self, ballot_id: BallotId, proof: WeightProof) -> Result<()> {
+            let caller = self.env().caller();
+            let ballot = self.ballot(ballot_id)?;
+            self.claim_weight(ballot_id, &ballot, caller, proof)
+        }
+
+
+        // Delegeate your vote to the voter `to`.
+        // Pass `proof` to claim your right to vote first.
+
+        #[ink(message)]
+        pub fn delegate(&mut self, ballot_id: BallotId, to: AccountId, proof: Option<WeightProof>) -> Result<()> {
+            self.delegate_split(ballot_id, vec![(to, FULL_SHARE)], proof)
+        }
+
+        /// Split your vote across several delegates, each
+        /// getting its share of your weight in parts of
+        /// `FULL_SHARE`. Shares have to add up to `FULL_SHARE`,
+        /// any remainder of the split goes to the last delegate.
+        /// Pass `proof` to claim your right to vote first.
         #[ink(message)]
-        pub fn vote(&mut self, proposal: i32) {
+        pub fn delegate_split(&mut self, ballot_id: BallotId, delegations: Vec<(AccountId, u32)>, proof: Option<WeightProof>) -> Result<()> {
             let caller = self.env().caller();
-            let mut voters = self.voters.get(caller).unwrap_or_default();
-            assert!(voters.weight != 0, "Has no right to vote");
-            assert!(!voters.voted, "Already voted.");
+            let mut ballot = self.ballot(ballot_id)?;
+            if let Some(proof) = proof {
+                self.claim_weight(ballot_id, &ballot, caller, proof)?;
+            }
+            if !matches!(ballot.phase(self.env().block_timestamp()), Phase::Registration | Phase::Proposal | Phase::Voting) {
+                return Err(BallotError::WrongPhase)
+            }
+            let mut voters = self.load_voter(ballot_id, &ballot, caller)?;
+            if voters.voted || voters.commitment.is_some() {
+                return Err(BallotError::AlreadyVoted)
+            }
+            if voters.weight == 0 {
+                return Err(BallotError::NoRightToVote)
+            }
+
+            // the longest chain into the caller gets longer
+            // by the chain behind each delegate
+            let incoming = self.chain_depth(ballot_id, caller);
+            let mut total_share: u32 = 0;
+            for (i, &(to, share)) in delegations.iter().enumerate() {
+                if to == caller {
+                    return Err(BallotError::SelfDelegation)
+                }
+                if delegations[..i].iter().any(|&(other, _)| other == to) {
+                    return Err(BallotError::DuplicateDelegate)
+                }
+                if share == 0 {
+                    return Err(BallotError::InvalidShares)
+                }
+                total_share = total_share.saturating_add(share);
+
+                // Voters cannot delegate to accounts that cannot vote.
+                if self.load_voter(ballot_id, &ballot, to)?.weight == 0 {
+                    return Err(BallotError::DelegateHasNoRightToVote)
+                }
+
+                let depth = self.chain_height(ballot_id, to, caller)?;
+                if incoming + 1 + depth > ballot.max_delegation_depth {
+                    return Err(BallotError::DelegationTooDeep)
+                }
+            }
+            if total_share != FULL_SHARE {
+                return Err(BallotError::InvalidShares)
+            }
+
             voters.voted = true;
-            voters.vote = proposal;
+            voters.delegations = delegations;
+            self.voters.insert((ballot_id, caller), &voters);
+            for &(to, _) in voters.delegations.iter() {
+                let mut delegators = self.delegators.get((ballot_id, to)).unwrap_or_default();
+                delegators.push(caller);
+                self.delegators.insert((ballot_id, to), &delegators);
+            }
+
+            // Everyone along the chains keeps track of the weight
+            // passing through, so it can be walked back later
+            for (to, weight) in split_weight(voters.weight, &voters.delegations) {
+                self.push_weight(ballot_id, &mut ballot, to, weight);
+                self.env().emit_event(Delegated{
+                    ballot_id,
+                    from: caller,
+                    to,
+                    weight,
+                });
+            }
+            self.ballots.insert(ballot_id, &ballot);
+            Ok(())
+        }
+
+        /// Take back your delegation while voting is open,
+        /// removing your weight from the delegates or from
+        /// the proposals they voted for. You can vote or
+        /// delegate again afterwards.
+        #[ink(message)]
+        pub fn undelegate(&mut self, ballot_id: BallotId) -> Result<()> {
+            let caller = self.env().caller();
+            let mut ballot = self.ballot(ballot_id)?;
+            if !matches!(ballot.phase(self.env().block_timestamp()), Phase::Registration | Phase::Proposal | Phase::Voting) {
+                return Err(BallotError::WrongPhase)
+            }
+            let mut voters = self.voters.get((ballot_id, caller)).unwrap_or_default();
+            if voters.delegations.is_empty() {
+                return Err(BallotError::NotDelegated)
+            }
+
+            for (to, weight) in split_weight(voters.weight, &voters.delegations) {
+                self.pull_weight(ballot_id, &mut ballot, to, weight);
+                let mut delegators = self.delegators.get((ballot_id, to)).unwrap_or_default();
+                delegators.retain(|&d| d != caller);
+                self.delegators.insert((ballot_id, to), &delegators);
+                self.env().emit_event(Undelegated{
+                    ballot_id,
+                    from: caller,
+                    to,
+                    weight,
+                });
+            }
+            self.ballots.insert(ballot_id, &ballot);
 
-            // if `proposal` is out of the range of the array,
-            // this will throw automatically and revert all changes
-            let mut _vote_count = self.proposals[proposal as usize].vote_count;
-            _vote_count += voters.weight;
+            voters.voted = false;
+            voters.delegations = Vec::new();
+            self.voters.insert((ballot_id, caller), &voters);
+            Ok(())
         }
 
-        // Computes the winning proposal taking all
-        // previous votes into account
+        /// Let `to` vote for you on proposals in `category`.
+        /// Unless you vote or delegate yourself first, your
+        /// vote goes to the proposal `to` votes for, as long
+        /// as it is in `category`. With delegates for several
+        /// categories, whoever votes first decides.
         #[ink(message)]
-        pub fn winning_proposal(&self) -> i32 {
-            let mut winning_vote_count = 0;
-            let mut _winning_proposal = 0;
-            let i = 0;
-            for i in i..self.proposals.len() as usize {
-                if self.proposals[i].vote_count > winning_vote_count {
-                    winning_vote_count = self.proposals[i].vote_count;
-                    _winning_proposal = i as i32;
+        pub fn delegate_category(&mut self, ballot_id: BallotId, category: Category, to: AccountId) -> Result<()> {
+            let caller = self.env().caller();
+            let mut ballot = self.ballot(ballot_id)?;
+            if !matches!(ballot.phase(self.env().block_timestamp()), Phase::Registration | Phase::Proposal | Phase::Voting) {
+                return Err(BallotError::WrongPhase)
+            }
+            if ballot.mode != VotingMode::Plurality {
+                return Err(BallotError::WrongMode)
+            }
+            let voters = self.load_voter(ballot_id, &ballot, caller)?;
+            if voters.voted {
+                return Err(BallotError::AlreadyVoted)
+            }
+            if voters.weight == 0 {
+                return Err(BallotError::NoRightToVote)
+            }
+            if to == caller {
+                return Err(BallotError::SelfDelegation)
+            }
+            let voters_to = self.load_voter(ballot_id, &ballot, to)?;
+            if voters_to.weight == 0 {
+                return Err(BallotError::DelegateHasNoRightToVote)
+            }
+
+            // forget about the previous delegate for this category
+            if let Some(previous) = self.category_delegates.get((ballot_id, caller, category)) {
+                let mut delegators = self.category_delegators.get((ballot_id, previous, category)).unwrap_or_default();
+                delegators.retain(|&d| d != caller);
+                self.category_delegators.insert((ballot_id, previous, category), &delegators);
+            }
+            self.category_delegates.insert((ballot_id, caller, category), &to);
+            let mut delegators = self.category_delegators.get((ballot_id, to, category)).unwrap_or_default();
+            delegators.push(caller);
+            self.category_delegators.insert((ballot_id, to, category), &delegators);
+            self.env().emit_event(CategoryDelegated{
+                ballot_id,
+                from: caller,
+                to,
+                category,
+            });
+
+            // the delegate may have voted in this category already,
+            // abstaining votes for no proposal
+            if voters_to.voted && voters_to.delegations.is_empty() && voters_to.abstention.is_none() {
+                let proposal = voters_to.vote;
+                if ballot.proposals[proposal as usize].category == category {
+                    self.resolve_category_votes(ballot_id, &mut ballot, to, proposal);
+                    self.ballots.insert(ballot_id, &ballot);
                 }
             }
+            Ok(())
+        }
 
-            _winning_proposal
+        /// How much of `voter`'s weight ends up with each
+        /// account at the end of its delegation chains.
+        /// A voter that did not delegate keeps all of it.
+        #[ink(message)]
+        pub fn weight_distribution(&self, ballot_id: BallotId, voter: AccountId) -> Result<Vec<(AccountId, u64)>> {
+            self.ballot(ballot_id)?;
+            let weight = self.voters.get((ballot_id, voter)).unwrap_or_default().weight;
+            let mut distribution = Vec::new();
+            self.distribute_weight(ballot_id, voter, weight, &mut distribution);
+            Ok(distribution)
         }
 
-        // Calls `winningProposal()` function to get the index
-        // of the winner contained in the proposals array and then 
-        // returns the name of the winner
+        /// The accounts `account`'s vote passes through, in
+        /// order, up to the one casting it. Split delegations
+        /// are followed along their largest share.
+        #[ink(message)]
+        pub fn delegation_chain(&self, ballot_id: BallotId, account: AccountId) -> Result<Vec<AccountId>> {
+            self.ballot(ballot_id)?;
+            let mut chain = Vec::new();
+            let mut voter = self.voters.get((ballot_id, account)).unwrap_or_default();
+            // the first largest share wins a tie
+            while let Some(&(to, _)) = voter.delegations.iter().rev().max_by_key(|&&(_, share)| share) {
+                chain.push(to);
+                voter = self.voters.get((ballot_id, to)).unwrap_or_default();
+            }
+            Ok(chain)
+        }
+
+        /// The account that ends up casting `account`'s vote,
+        /// the last one of its `delegation_chain`.
+        #[ink(message)]
+        pub fn effective_delegate(&self, ballot_id: BallotId, account: AccountId) -> Result<AccountId> {
+            Ok(self.delegation_chain(ballot_id, account)?.pop().unwrap_or(account))
+        }
+
+        // Give your vote (including votes delegated to you)
+        // to proposal `proposals[proposal].name`.
+        // Pass `proof` to claim your right to vote first.
         #[ink(message)]
-        pub fn winner_name(&self) -> String {
-            self.proposals[self.winning_proposal() as usize].name.clone()
+        pub fn vote(&mut self, ballot_id: BallotId, proposal: i32, proof: Option<WeightProof>) -> Result<()> {
+            let caller = self.env().caller();
+            let mut ballot = self.voting_ballot(ballot_id, VotingMode::Plurality)?;
+            if let Some(proof) = proof {
+                self.claim_weight(ballot_id, &ballot, caller, proof)?;
+            }
+            let voters = self.fresh_voter(ballot_id, &ballot, caller)?;
+            self.cast_vote(ballot_id, &mut ballot, caller, voters, proposal)
+        }
+
+        /// Vote for `proposal` with your own weight multiplied
+        /// by `2^conviction`, and the weight delegated to you
+        /// once. The transferred stake, at least
+        /// `conviction_stake` per unit of your own weight, is
+        /// locked for `conviction_period * 2^conviction` after
+        /// voting ended and can be taken back with `unlock`.
+        #[ink(message, payable)]
+        pub fn vote_with_conviction(&mut self, ballot_id: BallotId, proposal: i32, conviction: u32) -> Result<()> {
+            let caller = self.env().caller();
+            let mut ballot = self.voting_ballot(ballot_id, VotingMode::Plurality)?;
+            let mut voters = self.fresh_voter(ballot_id, &ballot, caller)?;
+            if conviction > MAX_CONVICTION {
+                return Err(BallotError::ConvictionTooHigh)
+            }
+            // delegators did not stake, so their weight
+            // can leave again without taking a bonus along
+            let own_weight = voters.weight.saturating_sub(voters.delegated_weight);
+            let stake = self.env().transferred_value();
+            if stake < ballot.conviction_stake.saturating_mul(own_weight as Balance) {
+                return Err(BallotError::NotEnoughStake)
+            }
+
+            ballot.check_proposal(proposal)?;
+            // the own weight counts 2^conviction times, the
+            // votes beyond the first time are the bonus
+            let bonus = own_weight
+                .checked_mul((1 << conviction) - 1)
+                .filter(|bonus| bonus.checked_add(voters.weight).is_some())
+                .ok_or(BallotError::WeightOverflow)?;
+
+            voters.conviction_bonus = bonus;
+            voters.conviction = conviction;
+            voters.locked_balance += stake;
+            self.reserved += stake;
+            let lock_end = ballot.voting_end + ballot.conviction_period * (1 << conviction);
+            voters.lock_expiry = voters.lock_expiry.max(lock_end);
+            self.cast_vote(ballot_id, &mut ballot, caller, voters, proposal)
         }
 
+        /// Take back the stake locked by your conviction
+        /// votes once the lock expired.
+        #[ink(message)]
+        pub fn unlock(&mut self, ballot_id: BallotId) -> Result<()> {
+            let caller = self.env().caller();
+            self.ballot(ballot_id)?;
+            let mut voters = self.voters.get((ballot_id, caller)).unwrap_or_default();
+            if voters.locked_balance == 0 {
+                return Err(BallotError::NothingLocked)
+            }
+            if self.env().block_timestamp() < voters.lock_expiry {
+                return Err(BallotError::StillLocked)
+            }
+
+            let amount = voters.locked_balance;
+            voters.locked_balance = 0;
+            self.voters.insert((ballot_id, caller), &voters);
+            self.reserved -= amount;
+            self.env().transfer(caller, amount).map_err(|_| BallotError::TransferFailed)?;
+            self.env().emit_event(Unlocked{
+                ballot_id,
+                voter: caller,
+                amount,
+            });
+            Ok(())
+        }
+
+        /// Count a vote signed off-chain by the voter, who
+        /// does not need to pay for it. The signature is an
+        /// ECDSA signature over `signed_vote_hash`, the voter
+        /// is the account of the signing key.
+        #[ink(message)]
+        pub fn vote_by_signature(&mut self, vote: SignedVote, signature: [u8; 65]) -> Result<()> {
+            if self.env().block_timestamp() > vote.deadline {
+                return Err(BallotError::SignatureExpired)
+            }
+            let hash = signed_vote_hash(self.env().account_id(), &vote);
+            let mut public_key = [0; 33];
+            ink_env::ecdsa_recover(&signature, &hash, &mut public_key)
+                .map_err(|_| BallotError::InvalidSignature)?;
+            let mut signer = [0; 32];
+            ink_env::hash_bytes::<ink_env::hash::Blake2x256>(&public_key, &mut signer);
+            let signer = AccountId::from(signer);
+
+            let nonce = self.nonces.get(signer).unwrap_or_default();
+            if vote.nonce != nonce {
+                return Err(BallotError::InvalidNonce)
+            }
+            let mut ballot = self.voting_ballot(vote.ballot_id, VotingMode::Plurality)?;
+            let voters = self.fresh_voter(vote.ballot_id, &ballot, signer)?;
+            self.cast_vote(vote.ballot_id, &mut ballot, signer, voters, vote.proposal)?;
+            self.nonces.insert(signer, &(nonce + 1));
+            Ok(())
+        }
+
+        /// the nonce the next signed vote of `voter` has to carry
+        #[ink(message)]
+        pub fn nonce(&self, voter: AccountId) -> u64 {
+            self.nonces.get(voter).unwrap_or_default()
+        }
+
+        /// Rank proposals in order of preference, most
+        /// preferred first. Proposals left out are ranked
+        /// below all listed ones.
+        #[ink(message)]
+        pub fn vote_ranked(&mut self, ballot_id: BallotId, ranking: Vec<u32>) -> Result<()> {
+            let caller = self.env().caller();
+            let mut ballot = self.voting_ballot(ballot_id, VotingMode::Ranked)?;
+            let mut voters = self.fresh_voter(ballot_id, &ballot, caller)?;
+            check_distinct_proposals(&ballot, &ranking)?;
+            voters.voted = true;
+            voters.choices = ranking;
+            self.voters.insert((ballot_id, caller), &voters);
+
+            self.count_vote(ballot_id, &mut ballot, &voters, voters.weight);
+            self.ballots.insert(ballot_id, &ballot);
+            self.env().emit_event(Voted{
+                ballot_id,
+                voter: caller,
+                proposal: voters.choices[0] as i32,
+                weight: voters.weight,
+            });
+            Ok(())
+        }
+
+        /// Approve any number of distinct proposals, each
+        /// of them receives your full weight.
+        #[ink(message)]
+        pub fn vote_approval(&mut self, ballot_id: BallotId, approved: Vec<u32>) -> Result<()> {
+            let caller = self.env().caller();
+            let mut ballot = self.voting_ballot(ballot_id, VotingMode::Approval)?;
+            let mut voters = self.fresh_voter(ballot_id, &ballot, caller)?;
+            check_distinct_proposals(&ballot, &approved)?;
+            voters.voted = true;
+            voters.choices = approved;
+            self.voters.insert((ballot_id, caller), &voters);
+
+            self.count_vote(ballot_id, &mut ballot, &voters, voters.weight);
+            self.ballots.insert(ballot_id, &ballot);
+            for &proposal in voters.choices.iter() {
+                self.env().emit_event(Voted{
+                    ballot_id,
+                    voter: caller,
+                    proposal: proposal as i32,
+                    weight: voters.weight,
+                });
+            }
+            Ok(())
+        }
+
+        /// Spread votes over proposals as a list of
+        /// `(proposal, votes)` pairs. Casting `n` votes
+        /// on a proposal costs `n * n` credits.
+        #[ink(message)]
+        pub fn vote_quadratic(&mut self, ballot_id: BallotId, allocation: Vec<(u32, u64)>) -> Result<()> {
+            let caller = self.env().caller();
+            let mut ballot = self.voting_ballot(ballot_id, VotingMode::Quadratic)?;
+            let mut voters = self.fresh_voter(ballot_id, &ballot, caller)?;
+            let proposals: Vec<u32> = allocation.iter().map(|&(p, _)| p).collect();
+            check_distinct_proposals(&ballot, &proposals)?;
+
+            let cost = quadratic_cost(&allocation)?;
+            if cost > voters.credits {
+                return Err(BallotError::NotEnoughCredits)
+            }
+            // the weight multiplies the votes bought
+            if allocation.iter().any(|&(_, votes)| votes.checked_mul(voters.weight).is_none()) {
+                return Err(BallotError::WeightOverflow)
+            }
+            voters.credits -= cost;
+            voters.voted = true;
+            voters.allocation = allocation;
+            self.voters.insert((ballot_id, caller), &voters);
+
+            self.count_vote(ballot_id, &mut ballot, &voters, voters.weight);
+            self.ballots.insert(ballot_id, &ballot);
+            for &(proposal, votes) in voters.allocation.iter() {
+                self.env().emit_event(Voted{
+                    ballot_id,
+                    voter: caller,
+                    proposal: proposal as i32,
+                    weight: votes.saturating_mul(voters.weight),
+                });
+            }
+            Ok(())
+        }
+
+        /// Abstain or reject every proposal, including the
+        /// weight delegated to you. Either counts towards the
+        /// quorum without supporting any proposal. Not
+        /// available on secret ballots.
+        #[ink(message)]
+        pub fn abstain(&mut self, ballot_id: BallotId, kind: Abstention) -> Result<()> {
+            let caller = self.env().caller();
+            let mut ballot = self.ballot(ballot_id)?;
+            if ballot.phase(self.env().block_timestamp()) != Phase::Voting {
+                return Err(BallotError::WrongPhase)
+            }
+            if ballot.mode == VotingMode::Secret {
+                return Err(BallotError::WrongMode)
+            }
+            let mut voters = self.fresh_voter(ballot_id, &ballot, caller)?;
+            voters.voted = true;
+            voters.abstention = Some(kind);
+            self.voters.insert((ballot_id, caller), &voters);
+
+            self.count_vote(ballot_id, &mut ballot, &voters, voters.weight);
+            self.ballots.insert(ballot_id, &ballot);
+            self.env().emit_event(Abstained{
+                ballot_id,
+                voter: caller,
+                kind,
+                weight: voters.weight,
+            });
+            Ok(())
+        }
+
+        /// Move your vote, including the weight delegated
+        /// to you, to another proposal while voting is open.
+        #[ink(message)]
+        pub fn change_vote(&mut self, ballot_id: BallotId, proposal: i32) -> Result<()> {
+            let caller = self.env().caller();
+            let mut ballot = self.voting_ballot(ballot_id, VotingMode::Plurality)?;
+            let mut voters = self.direct_voter(ballot_id, caller)?;
+            ballot.check_proposal(proposal)?;
+            if voters.abstention.is_none() {
+                self.unresolve_category_votes(ballot_id, &mut ballot, caller, voters.vote);
+            }
+            self.retract_vote(ballot_id, &mut ballot, &voters, voters.counted_weight());
+            self.env().emit_event(VoteRevoked{
+                ballot_id,
+                voter: caller,
+                weight: voters.counted_weight(),
+            });
+
+            voters.vote = proposal;
+            voters.abstention = None;
+            voters.following = None;
+            self.voters.insert((ballot_id, caller), &voters);
+            self.count_vote(ballot_id, &mut ballot, &voters, voters.counted_weight());
+            self.env().emit_event(Voted{
+                ballot_id,
+                voter: caller,
+                proposal,
+                weight: voters.counted_weight(),
+            });
+            self.resolve_category_votes(ballot_id, &mut ballot, caller, proposal);
+            self.ballots.insert(ballot_id, &ballot);
+            Ok(())
+        }
+
+        /// Take back your vote, including the weight delegated
+        /// to you, while voting is open. You can vote or
+        /// delegate again afterwards.
+        #[ink(message)]
+        pub fn revoke_vote(&mut self, ballot_id: BallotId) -> Result<()> {
+            let caller = self.env().caller();
+            let mut ballot = self.ballot(ballot_id)?;
+            if ballot.phase(self.env().block_timestamp()) != Phase::Voting {
+                return Err(BallotError::WrongPhase)
+            }
+            // revealed votes are only known once voting ended
+            if ballot.mode == VotingMode::Secret {
+                return Err(BallotError::WrongMode)
+            }
+            let mut voters = self.direct_voter(ballot_id, caller)?;
+            // category delegators lose the vote cast for them
+            if ballot.mode == VotingMode::Plurality && voters.abstention.is_none() {
+                self.unresolve_category_votes(ballot_id, &mut ballot, caller, voters.vote);
+            }
+            self.retract_vote(ballot_id, &mut ballot, &voters, voters.counted_weight());
+            self.ballots.insert(ballot_id, &ballot);
+
+            // credits spent on a quadratic ballot are refunded
+            voters.credits += quadratic_cost(&voters.allocation)?;
+            voters.voted = false;
+            voters.vote = 0;
+            voters.choices = Vec::new();
+            voters.allocation = Vec::new();
+            voters.abstention = None;
+            voters.following = None;
+            // the stake stays locked, only the bonus goes
+            voters.conviction = 0;
+            voters.conviction_bonus = 0;
+            self.voters.insert((ballot_id, caller), &voters);
+            self.env().emit_event(VoteRevoked{
+                ballot_id,
+                voter: caller,
+                weight: voters.weight,
+            });
+            Ok(())
+        }
+
+        /// Commit to a vote on a secret ballot, see
+        /// `vote_commitment`. The vote is only counted once
+        /// revealed with `reveal_vote` after voting ended.
+        #[ink(message)]
+        pub fn commit_vote(&mut self, ballot_id: BallotId, commitment: [u8; 32]) -> Result<()> {
+            let caller = self.env().caller();
+            let mut ballot = self.voting_ballot(ballot_id, VotingMode::Secret)?;
+            let mut voters = self.fresh_voter(ballot_id, &ballot, caller)?;
+            if voters.commitment.is_some() {
+                return Err(BallotError::AlreadyCommitted)
+            }
+            voters.commitment = Some(commitment);
+            self.voters.insert((ballot_id, caller), &voters);
+
+            ballot.commitments += 1;
+            self.ballots.insert(ballot_id, &ballot);
+            self.env().emit_event(VoteCommitted{
+                ballot_id,
+                voter: caller,
+            });
+            Ok(())
+        }
+
+        /// Reveal the vote committed to with `commit_vote`,
+        /// counting it with your full weight.
+        #[ink(message)]
+        pub fn reveal_vote(&mut self, ballot_id: BallotId, proposal: i32, salt: [u8; 32]) -> Result<()> {
+            let caller = self.env().caller();
+            let mut ballot = self.ballot(ballot_id)?;
+            if ballot.mode != VotingMode::Secret {
+                return Err(BallotError::WrongMode)
+            }
+            if ballot.phase(self.env().block_timestamp()) != Phase::Reveal {
+                return Err(BallotError::WrongPhase)
+            }
+            let mut voters = self.fresh_voter(ballot_id, &ballot, caller)?;
+            let commitment = voters.commitment.ok_or(BallotError::NoCommitment)?;
+            if commitment != vote_commitment(ballot_id, caller, proposal, salt) {
+                return Err(BallotError::CommitmentMismatch)
+            }
+            ballot.check_proposal(proposal)?;
+            voters.voted = true;
+            voters.vote = proposal;
+            self.voters.insert((ballot_id, caller), &voters);
+
+            self.count_vote(ballot_id, &mut ballot, &voters, voters.weight);
+            ballot.reveals += 1;
+            self.ballots.insert(ballot_id, &ballot);
+            self.env().emit_event(Voted{
+                ballot_id,
+                voter: caller,
+                proposal,
+                weight: voters.weight,
+            });
+            Ok(())
+        }
+
+        /// number of votes committed and revealed so far
+        /// on a secret ballot
+        #[ink(message)]
+        pub fn commitment_counts(&self, ballot_id: BallotId) -> Result<(u32, u32)> {
+            let ballot = self.ballot(ballot_id)?;
+            Ok((ballot.commitments, ballot.reveals))
+        }
+
+        /// close the ballot once voting ended, freezing
+        /// the winner. May only be called by `chairperson`
+        #[ink(message)]
+        pub fn close(&mut self, ballot_id: BallotId) -> Result<()> {
+            let caller = self.env().caller();
+            let mut ballot = self.ballot(ballot_id)?;
+            if caller != ballot.chair_person {
+                return Err(BallotError::NotChairperson)
+            }
+            if ballot.phase(self.env().block_timestamp()) != Phase::Tally {
+                return Err(BallotError::WrongPhase)
+            }
+
+            ballot.closed = true;
+            ballot.closed_at = self.env().block_timestamp();
+            self.ballots.insert(ballot_id, &ballot);
+            self.env().emit_event(BallotClosed{
+                ballot_id,
+                result: self.result(ballot_id)?,
+            });
+            Ok(())
+        }
+
+        /// Dispatch the call of the winning proposal once
+        /// the execution delay after closing passed. The
+        /// call is attempted once, its success is recorded
+        /// in the ballot. Anyone can trigger it.
+        #[ink(message)]
+        pub fn execute_winner(&mut self, ballot_id: BallotId) -> Result<()> {
+            let mut ballot = self.ballot(ballot_id)?;
+            if !ballot.closed {
+                return Err(BallotError::WrongPhase)
+            }
+            if self.env().block_timestamp() < ballot.closed_at + ballot.execution_delay {
+                return Err(BallotError::TimelockActive)
+            }
+            if ballot.execution.is_some() {
+                return Err(BallotError::AlreadyExecuted)
+            }
+            if ballot.veto.is_some() {
+                return Err(BallotError::Vetoed)
+            }
+            let (proposal, call) = self.winning_call(ballot_id, &ballot)?;
+            // locked stake and deposits are not the DAO's to spend
+            if self.env().balance() < self.reserved.saturating_add(call.transferred_value) {
+                return Err(BallotError::InsufficientFunds)
+            }
+
+            let success = build_call::<<Self as ::ink_lang::reflect::ContractEnv>::Env>()
+                .call_type(
+                    Call::new()
+                        .callee(call.callee)
+                        .gas_limit(0)
+                        .transferred_value(call.transferred_value),
+                )
+                .exec_input(
+                    ExecutionInput::new(Selector::new(call.selector))
+                        .push_arg(CallInput(&call.input)),
+                )
+                .returns::<()>()
+                .fire()
+                .is_ok();
+            ballot.execution = Some(success);
+            self.ballots.insert(ballot_id, &ballot);
+            self.env().emit_event(ProposalExecuted{
+                ballot_id,
+                proposal,
+                success,
+            });
+            Ok(())
+        }
+
+        /// Stop the winner's call from being executed, giving
+        /// `reason`. Possible until the execution delay after
+        /// closing is over. May only be called by a guardian
+        #[ink(message)]
+        pub fn veto(&mut self, ballot_id: BallotId, reason: String) -> Result<()> {
+            let caller = self.env().caller();
+            let mut ballot = self.ballot(ballot_id)?;
+            if !ballot.guardians.contains(&caller) {
+                return Err(BallotError::NotGuardian)
+            }
+            if !ballot.closed {
+                return Err(BallotError::WrongPhase)
+            }
+            if self.env().block_timestamp() >= ballot.closed_at + ballot.execution_delay {
+                return Err(BallotError::TimelockExpired)
+            }
+            if ballot.veto.is_some() {
+                return Err(BallotError::Vetoed)
+            }
+            self.winning_call(ballot_id, &ballot)?;
+
+            ballot.veto = Some((caller, reason.clone()));
+            self.ballots.insert(ballot_id, &ballot);
+            self.env().emit_event(ProposalVetoed{
+                ballot_id,
+                guardian: caller,
+                reason,
+            });
+            Ok(())
+        }
+
+        /// where the winner's call currently stands
+        #[ink(message)]
+        pub fn execution_state(&self, ballot_id: BallotId) -> Result<ExecutionState> {
+            let ballot = self.ballot(ballot_id)?;
+            let state = if !ballot.closed {
+                ExecutionState::Pending
+            } else if ballot.veto.is_some() {
+                ExecutionState::Vetoed
+            } else if let Some(success) = ballot.execution {
+                if success { ExecutionState::Executed } else { ExecutionState::Failed }
+            } else if self.winning_call(ballot_id, &ballot).is_ok() {
+                ExecutionState::Queued
+            } else {
+                ExecutionState::NoAction
+            };
+            Ok(state)
+        }
+
+        /// the phase the ballot is currently in
+        #[ink(message)]
+        pub fn phase(&self, ballot_id: BallotId) -> Result<Phase> {
+            Ok(self.ballot(ballot_id)?.phase(self.env().block_timestamp()))
+        }
+
+        // Computes the winning proposal taking all
+        // previous votes into account
+        #[ink(message)]
+        pub fn winning_proposal(&self, ballot_id: BallotId) -> Result<i32> {
+            let vote_counts = self.final_counts(&self.ballot(ballot_id)?, ballot_id);
+            let mut winning_vote_count = 0;
+            let mut _winning_proposal = 0;
+            let i = 0;
+            for i in i..vote_counts.len() as usize {
+                if vote_counts[i] > winning_vote_count {
+                    winning_vote_count = vote_counts[i];
+                    _winning_proposal = i as i32;
+                }
+            }
+
+            Ok(_winning_proposal)
+        }
+
+        /// The outcome of the ballot, taking its quorum
+        /// and threshold into account.
+        #[ink(message)]
+        pub fn result(&self, ballot_id: BallotId) -> Result<BallotResult> {
+            let ballot = self.ballot(ballot_id)?;
+            let vote_counts = self.final_counts(&ballot, ballot_id);
+            // approvals add up to more than the weight cast,
+            // so a proposal's share is taken of the turnout
+            let counted = match ballot.mode {
+                VotingMode::Approval => ballot.turnout.saturating_sub(ballot.abstentions),
+                _ => vote_counts.iter().sum::<u64>() + ballot.rejections,
+            };
+            Ok(ballot_result(&vote_counts, ballot.rejections, counted, ballot.turnout, ballot.quorum, ballot.threshold_percent))
+        }
+
+        /// The vote counts, abstentions and rejections
+        /// the ballot's result is decided on.
+        #[ink(message)]
+        pub fn tally(&self, ballot_id: BallotId) -> Result<Tally> {
+            let ballot = self.ballot(ballot_id)?;
+            Ok(Tally {
+                vote_counts: self.final_counts(&ballot, ballot_id),
+                abstentions: ballot.abstentions,
+                rejections: ballot.rejections,
+                turnout: ballot.turnout,
+            })
+        }
+
+        /// total weight of the votes counted so far
+        #[ink(message)]
+        pub fn turnout(&self, ballot_id: BallotId) -> Result<u64> {
+            Ok(self.ballot(ballot_id)?.turnout)
+        }
+
+        // Calls `winningProposal()` function to get the index
+        // of the winner contained in the proposals array and then
+        // returns the name of the winner
+        #[ink(message)]
+        pub fn winner_name(&self, ballot_id: BallotId) -> Result<String> {
+            let winner = self.winning_proposal(ballot_id)?;
+            self.ballot(ballot_id)?
+                .proposals
+                .get(winner as usize)
+                .map(|p| p.name.clone())
+                .ok_or(BallotError::ProposalOutOfRange)
+        }
+
+        /// The vote count of every proposal in each round of
+        /// the instant-runoff tally. Proposals eliminated in
+        /// an earlier round have a count of zero.
+        #[ink(message)]
+        pub fn runoff_rounds(&self, ballot_id: BallotId) -> Result<Vec<Vec<u64>>> {
+            let ballot = self.ballot(ballot_id)?;
+            Ok(instant_runoff(ballot.proposals.len(), &self.rankings(ballot_id)))
+        }
+
+        /// The proposal that beats every other proposal
+        /// head-to-head on the ranked ballots, if any.
+        #[ink(message)]
+        pub fn condorcet_winner(&self, ballot_id: BallotId) -> Result<Option<u32>> {
+            Ok(condorcet_winner(&self.pairwise_preferences(ballot_id)?))
+        }
+
+        /// All proposals ranked by the Schulze method,
+        /// winner first. Proposals tied by the method keep
+        /// their original order.
+        #[ink(message)]
+        pub fn schulze_ranking(&self, ballot_id: BallotId) -> Result<Vec<u32>> {
+            Ok(schulze_ranking(&self.pairwise_preferences(ballot_id)?))
+        }
+
+        /// the vote count of every proposal the winner is
+        /// decided on, the last runoff round on ranked ballots
+        fn final_counts(&self, ballot: &BallotInfo, ballot_id: BallotId) -> Vec<u64> {
+            match ballot.mode {
+                VotingMode::Plurality
+                | VotingMode::Quadratic
+                | VotingMode::Approval
+                | VotingMode::Secret => {
+                    ballot.proposals.iter().map(|p| p.vote_count).collect()
+                }
+                VotingMode::Ranked => {
+                    instant_runoff(ballot.proposals.len(), &self.rankings(ballot_id)).pop().unwrap_or_default()
+                }
+            }
+        }
+
+        /// the passed proposal and the call it carries
+        fn winning_call(&self, ballot_id: BallotId, ballot: &BallotInfo) -> Result<(u32, ProposalCall)> {
+            let proposal = match self.result(ballot_id)? {
+                BallotResult::Passed(proposal) => proposal,
+                _ => return Err(BallotError::NotPassed),
+            };
+            let call = ballot.proposals[proposal as usize]
+                .call
+                .clone()
+                .ok_or(BallotError::NoProposalCall)?;
+            Ok((proposal, call))
+        }
+
+        fn pairwise_preferences(&self, ballot_id: BallotId) -> Result<Vec<Vec<u64>>> {
+            let ballot = self.ballot(ballot_id)?;
+            Ok(pairwise_preferences(ballot.proposals.len(), &self.rankings(ballot_id)))
+        }
+
+        /// the rankings that still carry weight
+        fn rankings(&self, ballot_id: BallotId) -> Vec<RankedBallot> {
+            let count = self.ranked_ballot_count.get(ballot_id).unwrap_or_default();
+            (0..count)
+                .filter_map(|id| self.ranked_ballots.get((ballot_id, id)))
+                .filter(|ranked| ranked.weight > 0)
+                .collect()
+        }
+
+        /// store a new ballot and make `chair_person` its
+        /// first voter
+        fn add_ballot(&mut self, chair_person: AccountId, proposal_name: Vec<String>, config: BallotConfig) -> Result<BallotId> {
+            if let WeightSource::Token { snapshot_block, weight_unit, .. } = config.weight_source {
+                // a snapshot still to come would count tokens
+                // moved after the ballot started
+                if snapshot_block >= Self::env().block_number() {
+                    return Err(BallotError::SnapshotNotInPast)
+                }
+                if weight_unit == 0 {
+                    return Err(BallotError::ZeroWeightUnit)
+                }
+            }
+            if proposal_name.len() > config.max_proposals as usize {
+                return Err(BallotError::TooManyProposals)
+            }
+
+            let registration_end = config.registration_end;
+            let proposal_end = config.proposal_end;
+            let voting_end = config.voting_end;
+            let reveal_end = match config.mode {
+                VotingMode::Secret => config.reveal_end,
+                _ => voting_end,
+            };
+            if Self::env().block_timestamp() > registration_end
+                || registration_end > proposal_end
+                || proposal_end > voting_end
+                || voting_end > reveal_end
+            {
+                return Err(BallotError::InvalidSchedule)
+            }
+
+            // For each of the provided proposal names,
+            // create a new proposal object and add it
+            // to the end of the array
+            let mut proposals = Vec::new();
+            for name in proposal_name {
+                let proposal = Proposal {
+                    name,
+                    vote_count: 0,
+                    category: 0,
+                    call: None,
+                    proposer: None,
+                    description_hash: [0; 32],
+                    deposit: 0,
+                    rejected: false,
+                };
+                proposals.push(proposal);
+            }
+
+
+            let ballot_id = self.ballot_count;
+            self.ballot_count += 1;
+            self.ballots.insert(ballot_id, &BallotInfo {
+                chair_person,
+                proposals,
+                mode: config.mode,
+                weight_source: config.weight_source,
+                credit_budget: config.credit_budget,
+                max_proposals: config.max_proposals,
+                proposal_deposit: config.proposal_deposit,
+                registration_end,
+                proposal_end,
+                voting_end,
+                reveal_end,
+                max_delegation_depth: config.max_delegation_depth,
+                quorum: config.quorum,
+                threshold_percent: config.threshold_percent,
+                turnout: 0,
+                abstentions: 0,
+                rejections: 0,
+                commitments: 0,
+                reveals: 0,
+                voter_root: None,
+                closed: false,
+                closed_at: 0,
+                execution_delay: config.execution_delay,
+                execution: None,
+                guardians: config.guardians,
+                veto: None,
+                conviction_period: config.conviction_period,
+                conviction_stake: config.conviction_stake,
+            });
+
+            // token holders have to vote for themselves
+            if config.weight_source == WeightSource::Chairperson {
+                let voter = Voter {
+                    weight: 1,
+                    credits: config.credit_budget,
+                    ..Default::default()
+                };
+                self.voters.insert((ballot_id, chair_person), &voter);
+            }
+            Self::env().emit_event(BallotCreated{
+                ballot_id,
+                chair_person,
+            });
+            Ok(ballot_id)
+        }
+
+        fn ballot(&self, ballot_id: BallotId) -> Result<BallotInfo> {
+            self.ballots.get(ballot_id).ok_or(BallotError::BallotNotFound)
+        }
+
+        /// load a ballot that is open for votes in `mode`
+        fn voting_ballot(&self, ballot_id: BallotId, mode: VotingMode) -> Result<BallotInfo> {
+            let ballot = self.ballot(ballot_id)?;
+            if ballot.phase(self.env().block_timestamp()) != Phase::Voting {
+                return Err(BallotError::WrongPhase)
+            }
+            if ballot.mode != mode {
+                return Err(BallotError::WrongMode)
+            }
+            Ok(ballot)
+        }
+
+        /// grant `account` the weight proven to be part
+        /// of the ballot's voter allowlist
+        fn claim_weight(&mut self, ballot_id: BallotId, ballot: &BallotInfo, account: AccountId, proof: WeightProof) -> Result<()> {
+            if !matches!(ballot.phase(self.env().block_timestamp()), Phase::Registration | Phase::Proposal | Phase::Voting) {
+                return Err(BallotError::WrongPhase)
+            }
+            let root = ballot.voter_root.ok_or(BallotError::NoVoterRoot)?;
+            if !merkle::verify(&root, merkle::leaf(&account, proof.weight), &proof.proof) {
+                return Err(BallotError::InvalidProof)
+            }
+
+            self.register_voter(ballot_id, ballot, account, proof.weight)
+        }
+
+        /// give `account` the right to vote with `weight`
+        fn register_voter(&mut self, ballot_id: BallotId, ballot: &BallotInfo, account: AccountId, weight: u64) -> Result<()> {
+            let mut voter = self.voters.get((ballot_id, account)).unwrap_or_default();
+            if voter.voted {
+                return Err(BallotError::AlreadyVoted)
+            }
+            if voter.weight != 0 {
+                return Err(BallotError::AlreadyHasRightToVote)
+            }
+            if weight == 0 {
+                return Err(BallotError::ZeroWeight)
+            }
+
+            voter.weight = weight;
+            voter.credits = ballot.credit_budget;
+            self.voters.insert((ballot_id, account), &voter);
+            self.env().emit_event(RightToVoteGranted{
+                ballot_id,
+                voter: account,
+                weight,
+            });
+            Ok(())
+        }
+
+        /// load `account`, first giving it the weight it holds
+        /// on token-weighted and house-weighted ballots
+        fn load_voter(&mut self, ballot_id: BallotId, ballot: &BallotInfo, account: AccountId) -> Result<Voter> {
+            let voter = self.voters.get((ballot_id, account)).unwrap_or_default();
+            if voter.weight != 0 || voter.voted {
+                return Ok(voter)
+            }
+            let weight = match ballot.weight_source {
+                WeightSource::Chairperson => return Ok(voter),
+                WeightSource::Token { token, snapshot_block, weight_unit } => {
+                    let balance = self.balance_at(token, account, snapshot_block)?;
+                    u64::try_from(balance / weight_unit).map_err(|_| BallotError::WeightOverflow)?
+                }
+                WeightSource::Houses { housetoken } => self.claim_houses(ballot_id, housetoken, account),
+            };
+            // holders of nothing stay without a right to vote
+            if weight == 0 {
+                return Ok(voter)
+            }
+            self.register_voter(ballot_id, ballot, account, weight)?;
+            Ok(self.voters.get((ballot_id, account)).unwrap_or_default())
+        }
+
+        /// count every house `owner` holds that was not counted
+        /// on this ballot yet for them, returning their number
+        fn claim_houses(&mut self, ballot_id: BallotId, housetoken: AccountId, owner: AccountId) -> u64 {
+            let housetoken: HousetokenRef = FromAccountId::from_account_id(housetoken);
+            self.count_houses(ballot_id, owner, housetoken.houses_of(owner))
+        }
+
+        /// count `houses` for `owner`, skipping those
+        /// already counted on this ballot
+        fn count_houses(&mut self, ballot_id: BallotId, owner: AccountId, houses: Vec<HouseId>) -> u64 {
+            let mut weight = 0;
+            for house in houses {
+                if self.house_voters.get((ballot_id, house)).is_none() {
+                    self.house_voters.insert((ballot_id, house), &owner);
+                    weight += 1;
+                }
+            }
+            weight
+        }
+
+        /// the balance `owner` held of `token` at `block`
+        fn balance_at(&self, token: AccountId, owner: AccountId, block: BlockNumber) -> Result<Balance> {
+            build_call::<<Self as ::ink_lang::reflect::ContractEnv>::Env>()
+                .call_type(Call::new().callee(token).gas_limit(0))
+                .exec_input(
+                    ExecutionInput::new(Selector::new(ink_lang::selector_bytes!("PSP22Snapshot::balance_of_at")))
+                        .push_arg(owner)
+                        .push_arg(block),
+                )
+                .returns::<Balance>()
+                .fire()
+                .map_err(|_| BallotError::TokenQueryFailed)
+        }
+
+        /// record `voter`'s vote for `proposal` and count it,
+        /// together with the votes of their category delegators
+        fn cast_vote(&mut self, ballot_id: BallotId, ballot: &mut BallotInfo, account: AccountId, mut voter: Voter, proposal: i32) -> Result<()> {
+            ballot.check_proposal(proposal)?;
+            voter.voted = true;
+            voter.vote = proposal;
+            self.voters.insert((ballot_id, account), &voter);
+
+            self.count_vote(ballot_id, ballot, &voter, voter.counted_weight());
+            self.env().emit_event(Voted{
+                ballot_id,
+                voter: account,
+                proposal,
+                weight: voter.counted_weight(),
+            });
+            self.resolve_category_votes(ballot_id, ballot, account, proposal);
+            self.ballots.insert(ballot_id, &*ballot);
+            Ok(())
+        }
+
+        /// load a voter that has the right to vote
+        /// and did not vote yet
+        fn fresh_voter(&mut self, ballot_id: BallotId, ballot: &BallotInfo, account: AccountId) -> Result<Voter> {
+            let voter = self.load_voter(ballot_id, ballot, account)?;
+            if voter.weight == 0 {
+                return Err(BallotError::NoRightToVote)
+            }
+            if voter.voted {
+                return Err(BallotError::AlreadyVoted)
+            }
+            Ok(voter)
+        }
+
+        /// load a voter that voted themselves
+        /// rather than delegating
+        fn direct_voter(&self, ballot_id: BallotId, account: AccountId) -> Result<Voter> {
+            let voter = self.voters.get((ballot_id, account)).unwrap_or_default();
+            if !voter.voted {
+                return Err(BallotError::NotVoted)
+            }
+            if !voter.delegations.is_empty() {
+                return Err(BallotError::HasDelegated)
+            }
+            Ok(voter)
+        }
+
+        /// add `weight` to `account` and to everyone down its
+        /// delegation chains, counting it for the votes at the end
+        fn push_weight(&mut self, ballot_id: BallotId, ballot: &mut BallotInfo, account: AccountId, weight: u64) {
+            let mut voter = self.voters.get((ballot_id, account)).unwrap_or_default();
+            let before = voter.weight;
+            voter.weight += weight;
+            voter.delegated_weight += weight;
+            self.voters.insert((ballot_id, account), &voter);
+            if voter.delegations.is_empty() {
+                if voter.voted {
+                    self.count_vote(ballot_id, ballot, &voter, weight);
+                }
+            } else {
+                self.resplit_weight(ballot_id, ballot, &voter.delegations, before, voter.weight);
+            }
+        }
+
+        /// undo `push_weight`
+        fn pull_weight(&mut self, ballot_id: BallotId, ballot: &mut BallotInfo, account: AccountId, weight: u64) {
+            let mut voter = self.voters.get((ballot_id, account)).unwrap_or_default();
+            let before = voter.weight;
+            voter.weight = voter.weight.saturating_sub(weight);
+            voter.delegated_weight = voter.delegated_weight.saturating_sub(weight);
+            self.voters.insert((ballot_id, account), &voter);
+            if voter.delegations.is_empty() {
+                if voter.voted {
+                    self.retract_vote(ballot_id, ballot, &voter, weight);
+                }
+            } else {
+                self.resplit_weight(ballot_id, ballot, &voter.delegations, before, voter.weight);
+            }
+        }
+
+        /// move the weight held by `delegations` from the split
+        /// of `before` to the split of `after`. Delegates always
+        /// hold the split of their delegator's whole weight, so
+        /// an undelegation takes back exactly what was passed on
+        /// however rounding fell for each piece.
+        fn resplit_weight(&mut self, ballot_id: BallotId, ballot: &mut BallotInfo, delegations: &[(AccountId, u32)], before: u64, after: u64) {
+            let held = split_weight(before, delegations);
+            let owed = split_weight(after, delegations);
+            for (&(to, held), &(_, owed)) in held.iter().zip(owed.iter()) {
+                if owed > held {
+                    self.push_weight(ballot_id, ballot, to, owed - held);
+                } else if held > owed {
+                    self.pull_weight(ballot_id, ballot, to, held - owed);
+                }
+            }
+        }
+
+        /// follow `weight` from `account` down its delegation
+        /// chains, adding up what reaches each account at the end
+        fn distribute_weight(&self, ballot_id: BallotId, account: AccountId, weight: u64, distribution: &mut Vec<(AccountId, u64)>) {
+            let voter = self.voters.get((ballot_id, account)).unwrap_or_default();
+            if voter.delegations.is_empty() {
+                match distribution.iter_mut().find(|(other, _)| *other == account) {
+                    Some((_, total)) => *total += weight,
+                    None => distribution.push((account, weight)),
+                }
+            } else {
+                for (to, part) in split_weight(weight, &voter.delegations) {
+                    self.distribute_weight(ballot_id, to, part, distribution);
+                }
+            }
+        }
+
+        /// cast the vote of everyone who made `delegate` their
+        /// delegate for the category of `proposal` and did not
+        /// vote yet, and so on for their own category delegators
+        fn resolve_category_votes(&mut self, ballot_id: BallotId, ballot: &mut BallotInfo, delegate: AccountId, proposal: i32) {
+            let category = ballot.proposals[proposal as usize].category;
+            let mut pending = vec![delegate];
+            while let Some(delegate) = pending.pop() {
+                for delegator in self.category_delegators.get((ballot_id, delegate, category)).unwrap_or_default() {
+                    let mut voter = self.voters.get((ballot_id, delegator)).unwrap_or_default();
+                    if voter.voted || voter.weight == 0 {
+                        continue
+                    }
+                    voter.voted = true;
+                    voter.vote = proposal;
+                    voter.following = Some(delegate);
+                    self.voters.insert((ballot_id, delegator), &voter);
+
+                    self.count_vote(ballot_id, ballot, &voter, voter.weight);
+                    self.env().emit_event(Voted{
+                        ballot_id,
+                        voter: delegator,
+                        proposal,
+                        weight: voter.weight,
+                    });
+                    pending.push(delegator);
+                }
+            }
+        }
+
+        /// take back the votes `resolve_category_votes` cast for
+        /// the category delegators of `delegate` who voted for
+        /// `proposal`, and so on for their own category delegators
+        fn unresolve_category_votes(&mut self, ballot_id: BallotId, ballot: &mut BallotInfo, delegate: AccountId, proposal: i32) {
+            let category = ballot.proposals[proposal as usize].category;
+            let mut pending = vec![delegate];
+            while let Some(delegate) = pending.pop() {
+                for delegator in self.category_delegators.get((ballot_id, delegate, category)).unwrap_or_default() {
+                    let mut voter = self.voters.get((ballot_id, delegator)).unwrap_or_default();
+                    if voter.following != Some(delegate) {
+                        continue
+                    }
+                    self.retract_vote(ballot_id, ballot, &voter, voter.weight);
+                    voter.voted = false;
+                    voter.vote = 0;
+                    voter.following = None;
+                    self.voters.insert((ballot_id, delegator), &voter);
+                    self.env().emit_event(VoteRevoked{
+                        ballot_id,
+                        voter: delegator,
+                        weight: voter.weight,
+                    });
+                    pending.push(delegator);
+                }
+            }
+        }
+
+        /// the length of the longest delegation chain starting
+        /// at `from`, failing if any of them passes through `caller`
+        fn chain_height(&self, ballot_id: BallotId, from: AccountId, caller: AccountId) -> Result<u32> {
+            let mut height = 0;
+            let mut pending = vec![(from, 0)];
+            while let Some((account, depth)) = pending.pop() {
+                // We found a loop in the delegation, not allowed.
+                if account == caller {
+                    return Err(BallotError::DelegationLoop)
+                }
+                height = height.max(depth);
+                let voter = self.voters.get((ballot_id, account)).unwrap_or_default();
+                pending.extend(voter.delegations.iter().map(|&(to, _)| (to, depth + 1)));
+            }
+            Ok(height)
+        }
+
+        /// the length of the longest delegation chain ending
+        /// at `account`
+        fn chain_depth(&self, ballot_id: BallotId, account: AccountId) -> u32 {
+            let mut depth = 0;
+            let mut pending = vec![(account, 0)];
+            while let Some((account, length)) = pending.pop() {
+                depth = depth.max(length);
+                let delegators = self.delegators.get((ballot_id, account)).unwrap_or_default();
+                pending.extend(delegators.into_iter().map(|from| (from, length + 1)));
+            }
+            depth
+        }
+
+        /// add `weight` votes to whatever `voter` voted for
+        fn count_vote(&mut self, ballot_id: BallotId, ballot: &mut BallotInfo, voter: &Voter, weight: u64) {
+            ballot.turnout += weight;
+            if let Some(kind) = voter.abstention {
+                match kind {
+                    Abstention::Abstain => ballot.abstentions += weight,
+                    Abstention::RejectAll => ballot.rejections += weight,
+                }
+                return
+            }
+            match ballot.mode {
+                VotingMode::Plurality | VotingMode::Secret => {
+                    ballot.proposals[voter.vote as usize].vote_count += weight;
+                }
+                VotingMode::Ranked => {
+                    // identical rankings share a single entry
+                    let id = match self.ranked_ballot_ids.get((ballot_id, &voter.choices)) {
+                        Some(id) => id,
+                        None => {
+                            let id = self.ranked_ballot_count.get(ballot_id).unwrap_or_default();
+                            self.ranked_ballot_count.insert(ballot_id, &(id + 1));
+                            self.ranked_ballot_ids.insert((ballot_id, &voter.choices), &id);
+                            id
+                        }
+                    };
+                    let mut ranked = self.ranked_ballots.get((ballot_id, id)).unwrap_or_else(|| RankedBallot {
+                        ranking: voter.choices.clone(),
+                        weight: 0,
+                    });
+                    ranked.weight += weight;
+                    self.ranked_ballots.insert((ballot_id, id), &ranked);
+                }
+                VotingMode::Quadratic => {
+                    // delegated weight multiplies the votes bought
+                    for &(proposal, votes) in voter.allocation.iter() {
+                        ballot.proposals[proposal as usize].vote_count += votes.saturating_mul(weight);
+                    }
+                }
+                VotingMode::Approval => {
+                    for &proposal in voter.choices.iter() {
+                        ballot.proposals[proposal as usize].vote_count += weight;
+                    }
+                }
+            }
+        }
+
+        /// undo `count_vote`
+        fn retract_vote(&mut self, ballot_id: BallotId, ballot: &mut BallotInfo, voter: &Voter, weight: u64) {
+            ballot.turnout = ballot.turnout.saturating_sub(weight);
+            if let Some(kind) = voter.abstention {
+                match kind {
+                    Abstention::Abstain => ballot.abstentions = ballot.abstentions.saturating_sub(weight),
+                    Abstention::RejectAll => ballot.rejections = ballot.rejections.saturating_sub(weight),
+                }
+                return
+            }
+            match ballot.mode {
+                VotingMode::Plurality | VotingMode::Secret => {
+                    let proposal = &mut ballot.proposals[voter.vote as usize];
+                    proposal.vote_count = proposal.vote_count.saturating_sub(weight);
+                }
+                VotingMode::Ranked => {
+                    // the entry stays, empty, for the next vote alike
+                    if let Some(id) = self.ranked_ballot_ids.get((ballot_id, &voter.choices)) {
+                        if let Some(mut ranked) = self.ranked_ballots.get((ballot_id, id)) {
+                            ranked.weight = ranked.weight.saturating_sub(weight);
+                            self.ranked_ballots.insert((ballot_id, id), &ranked);
+                        }
+                    }
+                }
+                VotingMode::Quadratic => {
+                    for &(proposal, votes) in voter.allocation.iter() {
+                        let proposal = &mut ballot.proposals[proposal as usize];
+                        proposal.vote_count = proposal.vote_count.saturating_sub(votes.saturating_mul(weight));
+                    }
+                }
+                VotingMode::Approval => {
+                    for &proposal in voter.choices.iter() {
+                        let proposal = &mut ballot.proposals[proposal as usize];
+                        proposal.vote_count = proposal.vote_count.saturating_sub(weight);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Passes the already encoded arguments of a
+    /// `ProposalCall` on without encoding them again.
+    struct CallInput<'a>(&'a [u8]);
+
+    impl<'a> scale::Encode for CallInput<'a> {
+        fn encode_to<T: scale::Output + ?Sized>(&self, dest: &mut T) {
+            dest.write(self.0);
+        }
+    }
+
+    /// The commitment of `voter` to vote for `proposal` on
+    /// the secret ballot `ballot_id`, kept hidden by the
+    /// random `salt`. Binding it to the voter and ballot
+    /// keeps others from copying it.
+    pub fn vote_commitment(ballot_id: BallotId, voter: AccountId, proposal: i32, salt: [u8; 32]) -> [u8; 32] {
+        let mut output = [0; 32];
+        ink_env::hash_encoded::<ink_env::hash::Blake2x256, _>(&(ballot_id, voter, proposal, salt), &mut output);
+        output
+    }
+
+    /// The hash a voter signs to have `vote` relayed to
+    /// the ballot contract at `contract`.
+    pub fn signed_vote_hash(contract: AccountId, vote: &SignedVote) -> [u8; 32] {
+        let mut output = [0; 32];
+        ink_env::hash_encoded::<ink_env::hash::Blake2x256, _>(&(contract, vote), &mut output);
+        output
+    }
+
+    /// split `weight` between `delegations` by their shares,
+    /// the last delegate gets whatever is left by rounding down
+    fn split_weight(weight: u64, delegations: &[(AccountId, u32)]) -> Vec<(AccountId, u64)> {
+        let mut left = weight;
+        let mut parts = Vec::new();
+        for (i, &(to, share)) in delegations.iter().enumerate() {
+            let part = if i + 1 == delegations.len() {
+                left
+            } else {
+                (weight as u128 * share as u128 / FULL_SHARE as u128) as u64
+            };
+            left -= part;
+            parts.push((to, part));
+        }
+        parts
+    }
+
+    /// The outcome of a tally given the final `vote_counts`,
+    /// the weight that rejected every proposal, the votes
+    /// `counted` for the threshold and the weight cast as
+    /// `turnout`. Nobody voting never passes.
+    fn ballot_result(vote_counts: &[u64], rejections: u64, counted: u64, turnout: u64, quorum: u64, threshold_percent: u32) -> BallotResult {
+        if turnout == 0 || turnout < quorum {
+            return BallotResult::NoQuorum
+        }
+        // only abstentions were cast
+        if counted == 0 {
+            return BallotResult::BelowThreshold
+        }
+        let highest = vote_counts.iter().copied().max().unwrap_or_default();
+        if rejections > 0 && rejections >= highest {
+            return BallotResult::Rejected
+        }
+        let leaders: Vec<u32> = (0..vote_counts.len() as u32)
+            .filter(|&i| vote_counts[i as usize] == highest)
+            .collect();
+        if leaders.len() > 1 {
+            return BallotResult::Tie(leaders)
+        }
+        if (highest as u128) * 100 < threshold_percent as u128 * counted as u128 {
+            return BallotResult::BelowThreshold
+        }
+        BallotResult::Passed(leaders[0])
+    }
+
+    /// the credits needed to buy `allocation` on a
+    /// quadratic ballot
+    fn quadratic_cost(allocation: &[(u32, u64)]) -> Result<u64> {
+        let mut cost: u64 = 0;
+        for &(_, votes) in allocation.iter() {
+            cost = votes
+                .checked_mul(votes)
+                .and_then(|c| cost.checked_add(c))
+                .ok_or(BallotError::NotEnoughCredits)?;
+        }
+        Ok(cost)
+    }
+
+    /// make sure `proposals` is a non-empty list of
+    /// distinct, existing proposal indices of `ballot`
+    fn check_distinct_proposals(ballot: &BallotInfo, proposals: &[u32]) -> Result<()> {
+        if proposals.is_empty() {
+            return Err(BallotError::NoProposalSelected)
+        }
+        for (i, &p) in proposals.iter().enumerate() {
+            ballot.check_proposal(p as i32)?;
+            if proposals[..i].contains(&p) {
+                return Err(BallotError::DuplicateProposal)
+            }
+        }
+        Ok(())
+    }
+
+    /// The vote count of every proposal in each round of
+    /// an instant-runoff tally over `n` proposals.
+    fn instant_runoff(n: usize, ballots: &[RankedBallot]) -> Vec<Vec<u64>> {
+        let mut eliminated = vec![false; n];
+        let mut remaining = n;
+        let mut rounds = Vec::new();
+        while remaining > 0 {
+            // every ballot counts for its most preferred
+            // proposal that is still in the race
+            let mut vote_counts = vec![0; n];
+            let mut active_weight = 0;
+            for ballot in ballots {
+                if let Some(&p) = ballot.ranking.iter().find(|&&p| !eliminated[p as usize]) {
+                    vote_counts[p as usize] += ballot.weight;
+                    active_weight += ballot.weight;
+                }
+            }
+
+            let mut leader = None;
+            let mut last = None;
+            for i in (0..n).filter(|&i| !eliminated[i]) {
+                if leader.map_or(true, |l| vote_counts[i] > vote_counts[l]) {
+                    leader = Some(i);
+                }
+                // ties are broken by eliminating the later proposal
+                if last.map_or(true, |l| vote_counts[i] <= vote_counts[l]) {
+                    last = Some(i);
+                }
+            }
+            let majority = leader.map_or(false, |l| vote_counts[l] * 2 > active_weight);
+            rounds.push(vote_counts);
+
+            if majority || remaining == 1 || active_weight == 0 {
+                break
+            }
+            if let Some(l) = last {
+                eliminated[l] = true;
+            }
+            remaining -= 1;
+        }
+        rounds
+    }
+
+    /// `d[i][j]` is the weight of the ballots that rank
+    /// proposal `i` above proposal `j`. Proposals left
+    /// out of a ranking are ranked below all listed ones.
+    fn pairwise_preferences(n: usize, ballots: &[RankedBallot]) -> Vec<Vec<u64>> {
+        let mut d = vec![vec![0; n]; n];
+        for ballot in ballots {
+            let mut position = vec![usize::MAX; n];
+            for (k, &p) in ballot.ranking.iter().enumerate() {
+                position[p as usize] = k;
+            }
+            for i in 0..n {
+                for j in 0..n {
+                    if position[i] < position[j] {
+                        d[i][j] += ballot.weight;
+                    }
+                }
+            }
+        }
+        d
+    }
+
+    fn condorcet_winner(d: &[Vec<u64>]) -> Option<u32> {
+        let n = d.len();
+        (0..n)
+            .find(|&i| (0..n).all(|j| i == j || d[i][j] > d[j][i]))
+            .map(|i| i as u32)
+    }
+
+    fn schulze_ranking(d: &[Vec<u64>]) -> Vec<u32> {
+        let n = d.len();
+
+        // strength of the strongest path from `i` to `j`
+        let mut p = vec![vec![0; n]; n];
+        for i in 0..n {
+            for j in 0..n {
+                if d[i][j] > d[j][i] {
+                    p[i][j] = d[i][j];
+                }
+            }
+        }
+        for k in 0..n {
+            for i in (0..n).filter(|&i| i != k) {
+                for j in (0..n).filter(|&j| j != i && j != k) {
+                    p[i][j] = p[i][j].max(p[i][k].min(p[k][j]));
+                }
+            }
+        }
+
+        // the more proposals one beats, the higher it ranks
+        let wins: Vec<usize> = (0..n)
+            .map(|i| (0..n).filter(|&j| p[i][j] > p[j][i]).count())
+            .collect();
+        let mut ranking: Vec<u32> = (0..n as u32).collect();
+        ranking.sort_by(|&a, &b| wins[b as usize].cmp(&wins[a as usize]));
+        ranking
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use ink_lang as ink;
+
+        type Env = ink_env::DefaultEnvironment;
+
+        fn ballot(ranking: &[u32], weight: u64) -> RankedBallot {
+            RankedBallot {
+                ranking: ranking.to_vec(),
+                weight,
+            }
+        }
+
+        #[test]
+        fn condorcet_winner_beats_everyone_head_to_head() {
+            let ballots = vec![ballot(&[0, 1, 2], 2), ballot(&[1, 2, 0], 1)];
+            let d = pairwise_preferences(3, &ballots);
+            assert_eq!(condorcet_winner(&d), Some(0));
+            assert_eq!(schulze_ranking(&d), vec![0, 1, 2]);
+        }
+
+        #[test]
+        fn symmetric_cycle_has_no_winner() {
+            // A > B > C, B > C > A and C > A > B
+            let ballots = vec![
+                ballot(&[0, 1, 2], 1),
+                ballot(&[1, 2, 0], 1),
+                ballot(&[2, 0, 1], 1),
+            ];
+            let d = pairwise_preferences(3, &ballots);
+            assert_eq!(condorcet_winner(&d), None);
+            assert_eq!(schulze_ranking(&d), vec![0, 1, 2]);
+        }
+
+        #[test]
+        fn schulze_resolves_weighted_cycles() {
+            // the example election from the Schulze method
+            // article, with A = 0 up to E = 4
+            let ballots = vec![
+                ballot(&[0, 2, 1, 4, 3], 5),
+                ballot(&[0, 3, 4, 2, 1], 5),
+                ballot(&[1, 4, 3, 0, 2], 8),
+                ballot(&[2, 0, 1, 4, 3], 3),
+                ballot(&[2, 0, 4, 1, 3], 7),
+                ballot(&[2, 1, 0, 3, 4], 2),
+                ballot(&[3, 2, 4, 1, 0], 7),
+                ballot(&[4, 1, 0, 3, 2], 8),
+            ];
+            let d = pairwise_preferences(5, &ballots);
+            assert_eq!(condorcet_winner(&d), None);
+            assert_eq!(schulze_ranking(&d), vec![4, 0, 2, 1, 3]);
+        }
+
+        #[test]
+        fn unranked_proposals_lose_to_ranked_ones() {
+            let ballots = vec![ballot(&[2], 1)];
+            let d = pairwise_preferences(3, &ballots);
+            assert_eq!(condorcet_winner(&d), Some(2));
+            assert_eq!(d[0][1], 0);
+            assert_eq!(d[1][0], 0);
+        }
+
+        fn accounts() -> ink_env::test::DefaultAccounts<Env> {
+            ink_env::test::default_accounts::<Env>()
+        }
+
+        fn set_caller(caller: AccountId) {
+            ink_env::test::set_caller::<Env>(caller);
+        }
+
+        /// a chairperson-weighted ballot that opens for voting
+        /// after the first blocks and stays open long enough
+        fn config(mode: VotingMode) -> BallotConfig {
+            BallotConfig {
+                mode,
+                weight_source: WeightSource::Chairperson,
+                registration_end: 10,
+                proposal_end: 10,
+                max_proposals: 8,
+                proposal_deposit: 0,
+                voting_end: 1_000_000,
+                credit_budget: 0,
+                reveal_end: 0,
+                max_delegation_depth: 4,
+                quorum: 0,
+                threshold_percent: 0,
+                execution_delay: 0,
+                guardians: Vec::new(),
+                conviction_period: 0,
+                conviction_stake: 0,
+            }
+        }
+
+        fn proposals(n: usize) -> Vec<String> {
+            (0..n).map(|i| format!("proposal {}", i)).collect()
+        }
+
+        /// advance blocks until the block timestamp reached `time`
+        fn advance_to(time: Timestamp) {
+            while ink_env::block_timestamp::<Env>() < time {
+                ink_env::test::advance_block::<Env>();
+            }
+        }
+
+        fn start_voting(contract: &Ballot, ballot_id: BallotId) {
+            advance_to(contract.ballots.get(ballot_id).unwrap().proposal_end);
+        }
+
+        fn end_voting(contract: &Ballot, ballot_id: BallotId) {
+            advance_to(contract.ballots.get(ballot_id).unwrap().voting_end);
+        }
+
+        fn weight(contract: &Ballot, ballot_id: BallotId, voter: AccountId) -> u64 {
+            contract.voters.get((ballot_id, voter)).unwrap_or_default().weight
+        }
+
+        fn vote_counts(contract: &Ballot, ballot_id: BallotId) -> Vec<u64> {
+            contract.ballots.get(ballot_id).unwrap().proposals.iter().map(|p| p.vote_count).collect()
+        }
+
+        #[ink::test]
+        fn split_delegation_follows_the_shares() {
+            let accounts = accounts();
+            let mut contract = Ballot::new(proposals(2), config(VotingMode::Plurality));
+            let voters = vec![(accounts.bob, 4), (accounts.charlie, 1), (accounts.django, 1)];
+            assert_eq!(contract.give_rights_to_vote(0, voters), Ok(Vec::new()));
+            start_voting(&contract, 0);
+
+            set_caller(accounts.charlie);
+            assert_eq!(contract.vote(0, 0, None), Ok(()));
+            set_caller(accounts.django);
+            assert_eq!(contract.vote(0, 1, None), Ok(()));
+            set_caller(accounts.bob);
+            let shares = vec![(accounts.charlie, FULL_SHARE / 4 * 3), (accounts.django, FULL_SHARE / 4)];
+            assert_eq!(contract.delegate_split(0, shares, None), Ok(()));
+            assert_eq!(vote_counts(&contract, 0), vec![4, 2]);
+            assert_eq!(
+                contract.weight_distribution(0, accounts.bob),
+                Ok(vec![(accounts.charlie, 3), (accounts.django, 1)])
+            );
+
+            assert_eq!(contract.undelegate(0), Ok(()));
+            assert_eq!(vote_counts(&contract, 0), vec![1, 1]);
+            assert_eq!(weight(&contract, 0, accounts.charlie), 1);
+            assert_eq!(weight(&contract, 0, accounts.django), 1);
+        }
+
+        #[ink::test]
+        fn undelegation_takes_back_what_each_piece_passed_on() {
+            let accounts = accounts();
+            let mut contract = Ballot::new(proposals(2), config(VotingMode::Plurality));
+            let voters = vec![(accounts.bob, 1), (accounts.charlie, 1), (accounts.django, 1), (accounts.eve, 1)];
+            assert_eq!(contract.give_rights_to_vote(0, voters), Ok(Vec::new()));
+
+            // a weight of 1 split in halves passes all of it to django
+            set_caller(accounts.bob);
+            let halves = vec![(accounts.charlie, FULL_SHARE / 2), (accounts.django, FULL_SHARE / 2)];
+            assert_eq!(contract.delegate_split(0, halves, None), Ok(()));
+            assert_eq!(weight(&contract, 0, accounts.charlie), 1);
+            assert_eq!(weight(&contract, 0, accounts.django), 2);
+
+            // one more weight makes it one each
+            set_caller(accounts.eve);
+            assert_eq!(contract.delegate(0, accounts.bob, None), Ok(()));
+            assert_eq!(weight(&contract, 0, accounts.charlie), 2);
+            assert_eq!(weight(&contract, 0, accounts.django), 2);
+
+            set_caller(accounts.bob);
+            assert_eq!(contract.undelegate(0), Ok(()));
+            assert_eq!(weight(&contract, 0, accounts.bob), 2);
+            assert_eq!(weight(&contract, 0, accounts.charlie), 1);
+            assert_eq!(weight(&contract, 0, accounts.django), 1);
+
+            set_caller(accounts.eve);
+            assert_eq!(contract.undelegate(0), Ok(()));
+            assert_eq!(weight(&contract, 0, accounts.bob), 1);
+        }
+
+        #[ink::test]
+        fn category_delegators_follow_changed_and_revoked_votes() {
+            let accounts = accounts();
+            let mut contract = Ballot::new(proposals(2), config(VotingMode::Plurality));
+            let voters = vec![(accounts.bob, 1), (accounts.charlie, 1)];
+            assert_eq!(contract.give_rights_to_vote(0, voters), Ok(Vec::new()));
+            set_caller(accounts.charlie);
+            assert_eq!(contract.delegate_category(0, 0, accounts.bob), Ok(()));
+            start_voting(&contract, 0);
+
+            set_caller(accounts.bob);
+            assert_eq!(contract.vote(0, 0, None), Ok(()));
+            assert_eq!(vote_counts(&contract, 0), vec![2, 0]);
+            assert_eq!(contract.change_vote(0, 1), Ok(()));
+            assert_eq!(vote_counts(&contract, 0), vec![0, 2]);
+            assert_eq!(contract.revoke_vote(0), Ok(()));
+            assert_eq!(vote_counts(&contract, 0), vec![0, 0]);
+            assert!(!contract.voters.get((0, accounts.charlie)).unwrap().voted);
+        }
+
+        #[ink::test]
+        fn undelegating_shortens_delegation_chains() {
+            let accounts = accounts();
+            let mut ballot_config = config(VotingMode::Plurality);
+            ballot_config.max_delegation_depth = 2;
+            let mut contract = Ballot::new(proposals(2), ballot_config);
+            let voters = vec![(accounts.bob, 1), (accounts.charlie, 1), (accounts.django, 1)];
+            assert_eq!(contract.give_rights_to_vote(0, voters), Ok(Vec::new()));
+
+            // alice -> bob -> charlie is as long as allowed
+            assert_eq!(contract.delegate(0, accounts.bob, None), Ok(()));
+            set_caller(accounts.bob);
+            assert_eq!(contract.delegate(0, accounts.charlie, None), Ok(()));
+            set_caller(accounts.charlie);
+            assert_eq!(contract.delegate(0, accounts.django, None), Err(BallotError::DelegationTooDeep));
+            assert_eq!(contract.delegation_chain(0, accounts.alice), Ok(vec![accounts.bob, accounts.charlie]));
+
+            // without alice's delegation there is room again
+            set_caller(accounts.alice);
+            assert_eq!(contract.undelegate(0), Ok(()));
+            set_caller(accounts.charlie);
+            assert_eq!(contract.delegate(0, accounts.django, None), Ok(()));
+            assert_eq!(contract.effective_delegate(0, accounts.bob), Ok(accounts.django));
+        }
+
+        #[ink::test]
+        fn ranked_ballots_pass_the_runoff_winner() {
+            let accounts = accounts();
+            let mut ballot_config = config(VotingMode::Ranked);
+            ballot_config.quorum = 3;
+            let mut contract = Ballot::new(proposals(3), ballot_config);
+            let voters = vec![(accounts.bob, 1), (accounts.charlie, 1), (accounts.django, 1)];
+            assert_eq!(contract.give_rights_to_vote(0, voters), Ok(Vec::new()));
+            start_voting(&contract, 0);
+
+            // proposal 2 is eliminated first, its ballot goes to proposal 0
+            assert_eq!(contract.vote_ranked(0, vec![0, 1]), Ok(()));
+            set_caller(accounts.bob);
+            assert_eq!(contract.vote_ranked(0, vec![0]), Ok(()));
+            assert_eq!(contract.result(0), Ok(BallotResult::NoQuorum));
+            set_caller(accounts.charlie);
+            assert_eq!(contract.vote_ranked(0, vec![1]), Ok(()));
+            set_caller(accounts.django);
+            assert_eq!(contract.vote_ranked(0, vec![2, 0]), Ok(()));
+
+            assert_eq!(contract.turnout(0), Ok(4));
+            assert_eq!(contract.runoff_rounds(0), Ok(vec![vec![2, 1, 1], vec![3, 1, 0]]));
+            assert_eq!(contract.result(0), Ok(BallotResult::Passed(0)));
+        }
+
+        #[ink::test]
+        fn token_ballots_need_a_past_snapshot() {
+            let accounts = accounts();
+            let mut contract = Ballot::new_registry();
+            let mut ballot_config = config(VotingMode::Plurality);
+            ballot_config.weight_source = WeightSource::Token {
+                token: accounts.frank,
+                snapshot_block: 0,
+                weight_unit: 1,
+            };
+            assert_eq!(contract.create_ballot(proposals(2), ballot_config.clone()), Err(BallotError::SnapshotNotInPast));
+
+            ink_env::test::advance_block::<Env>();
+            assert_eq!(contract.create_ballot(proposals(2), ballot_config), Ok(0));
+            assert_eq!(contract.get_voter(0, accounts.alice), None);
+        }
+
+        #[ink::test]
+        fn conviction_multiplies_only_the_staked_weight() {
+            let accounts = accounts();
+            let mut contract = Ballot::new(proposals(2), config(VotingMode::Plurality));
+            let voters = vec![(accounts.bob, 1)];
+            assert_eq!(contract.give_rights_to_vote(0, voters), Ok(Vec::new()));
+            start_voting(&contract, 0);
+
+            // alice's weight of 1 counts 4 times
+            assert_eq!(contract.vote_with_conviction(0, 1, 2), Ok(()));
+            assert_eq!(vote_counts(&contract, 0), vec![0, 4]);
+
+            // bob's weight arrives later and counts once
+            set_caller(accounts.bob);
+            assert_eq!(contract.delegate(0, accounts.alice, None), Ok(()));
+            assert_eq!(vote_counts(&contract, 0), vec![0, 5]);
+            assert_eq!(contract.undelegate(0), Ok(()));
+            assert_eq!(vote_counts(&contract, 0), vec![0, 4]);
+
+            set_caller(accounts.alice);
+            assert_eq!(contract.revoke_vote(0), Ok(()));
+            assert_eq!(vote_counts(&contract, 0), vec![0, 0]);
+        }
+
+        #[ink::test]
+        fn initial_proposals_respect_the_cap() {
+            let mut contract = Ballot::new_registry();
+            let mut ballot_config = config(VotingMode::Plurality);
+            ballot_config.max_proposals = 2;
+            assert_eq!(contract.create_ballot(proposals(3), ballot_config.clone()), Err(BallotError::TooManyProposals));
+            assert_eq!(contract.create_ballot(proposals(2), ballot_config), Ok(0));
+        }
+
+        #[ink::test]
+        fn messages_are_gated_by_the_phase() {
+            let accounts = accounts();
+            let mut contract = Ballot::new(proposals(2), config(VotingMode::Plurality));
+            assert_eq!(contract.phase(0), Ok(Phase::Registration));
+            assert_eq!(contract.vote(0, 0, None), Err(BallotError::WrongPhase));
+
+            start_voting(&contract, 0);
+            assert_eq!(contract.phase(0), Ok(Phase::Voting));
+            assert_eq!(contract.give_right_to_vote(0, accounts.bob), Err(BallotError::WrongPhase));
+            assert_eq!(contract.close(0), Err(BallotError::WrongPhase));
+            assert_eq!(contract.vote(0, 0, None), Ok(()));
+
+            end_voting(&contract, 0);
+            assert_eq!(contract.phase(0), Ok(Phase::Tally));
+            assert_eq!(contract.vote(0, 1, None), Err(BallotError::WrongPhase));
+            assert_eq!(contract.close(0), Ok(()));
+            assert_eq!(contract.phase(0), Ok(Phase::Closed));
+        }
+
+        /// the account of `key`, as `vote_by_signature` recovers it
+        fn signer(key: &secp256k1::SecretKey) -> AccountId {
+            let public_key = secp256k1::PublicKey::from_secret_key(&secp256k1::Secp256k1::new(), key);
+            let mut account = [0; 32];
+            ink_env::hash_bytes::<ink_env::hash::Blake2x256>(&public_key.serialize(), &mut account);
+            AccountId::from(account)
+        }
+
+        fn sign(vote: &SignedVote, key: &secp256k1::SecretKey) -> [u8; 65] {
+            let hash = signed_vote_hash(ink_env::account_id::<Env>(), vote);
+            let message = secp256k1::Message::from_slice(&hash).unwrap();
+            let (recovery_id, compact) = secp256k1::Secp256k1::new()
+                .sign_ecdsa_recoverable(&message, key)
+                .serialize_compact();
+            let mut signature = [0; 65];
+            signature[..64].copy_from_slice(&compact);
+            signature[64] = recovery_id.to_i32() as u8;
+            signature
+        }
+
+        #[ink::test]
+        fn signed_votes_are_relayed_once() {
+            let key = secp256k1::SecretKey::from_slice(&[7; 32]).unwrap();
+            let voter = signer(&key);
+            let mut contract = Ballot::new(proposals(2), config(VotingMode::Plurality));
+            assert_eq!(contract.give_rights_to_vote(0, vec![(voter, 1)]), Ok(Vec::new()));
+            start_voting(&contract, 0);
+
+            let now = ink_env::block_timestamp::<Env>();
+            let vote = SignedVote { ballot_id: 0, proposal: 1, nonce: 0, deadline: now };
+            let signature = sign(&vote, &key);
+
+            // a changed vote recovers some other account
+            let tampered = SignedVote { proposal: 0, ..vote.clone() };
+            assert_eq!(contract.vote_by_signature(tampered, signature), Err(BallotError::NoRightToVote));
+
+            assert_eq!(contract.vote_by_signature(vote.clone(), signature), Ok(()));
+            assert_eq!(contract.nonce(voter), 1);
+            assert_eq!(vote_counts(&contract, 0), vec![0, 1]);
+            assert_eq!(contract.vote_by_signature(vote, signature), Err(BallotError::InvalidNonce));
+
+            // past its deadline a signed vote is refused
+            let late = SignedVote { ballot_id: 0, proposal: 0, nonce: 1, deadline: now };
+            let signature = sign(&late, &key);
+            ink_env::test::advance_block::<Env>();
+            assert_eq!(contract.vote_by_signature(late, signature), Err(BallotError::SignatureExpired));
+        }
+
+        #[ink::test]
+        fn phase_end_times_have_to_be_in_order() {
+            let mut contract = Ballot::new_registry();
+            let mut ballot_config = config(VotingMode::Plurality);
+            ballot_config.proposal_end = 5;
+            assert_eq!(contract.create_ballot(proposals(2), ballot_config.clone()), Err(BallotError::InvalidSchedule));
+
+            // the registration cannot have ended already
+            ballot_config.proposal_end = 10;
+            advance_to(11);
+            assert_eq!(contract.create_ballot(proposals(2), ballot_config.clone()), Err(BallotError::InvalidSchedule));
+
+            ballot_config.registration_end = 20;
+            ballot_config.proposal_end = 20;
+            assert_eq!(contract.create_ballot(proposals(2), ballot_config), Ok(0));
+            assert_eq!(contract.phase(0), Ok(Phase::Registration));
+        }
+
+        #[ink::test]
+        fn quadratic_votes_cost_their_square_and_are_refunded() {
+            let accounts = accounts();
+            let mut ballot_config = config(VotingMode::Quadratic);
+            ballot_config.credit_budget = 10;
+            let mut contract = Ballot::new(proposals(2), ballot_config);
+            let voters = vec![(accounts.bob, 2), (accounts.charlie, u64::MAX)];
+            assert_eq!(contract.give_rights_to_vote(0, voters), Ok(Vec::new()));
+            start_voting(&contract, 0);
+
+            assert_eq!(contract.vote_quadratic(0, vec![(0, 3), (1, 1)]), Ok(()));
+            assert_eq!(vote_counts(&contract, 0), vec![3, 1]);
+
+            // bob's two votes cost 4 credits and count twice
+            set_caller(accounts.bob);
+            assert_eq!(contract.vote_quadratic(0, vec![(0, 4)]), Err(BallotError::NotEnoughCredits));
+            assert_eq!(contract.vote_quadratic(0, vec![(1, 2)]), Ok(()));
+            assert_eq!(vote_counts(&contract, 0), vec![3, 5]);
+            assert_eq!(contract.get_voter(0, accounts.bob).unwrap().credits, 6);
+
+            assert_eq!(contract.revoke_vote(0), Ok(()));
+            assert_eq!(vote_counts(&contract, 0), vec![3, 1]);
+            assert_eq!(contract.get_voter(0, accounts.bob).unwrap().credits, 10);
+
+            // votes that cannot be counted leave the voter untouched
+            set_caller(accounts.charlie);
+            assert_eq!(contract.vote_quadratic(0, vec![(0, 2)]), Err(BallotError::WeightOverflow));
+            let charlie = contract.get_voter(0, accounts.charlie).unwrap();
+            assert!(!charlie.voted);
+            assert_eq!(charlie.credits, 10);
+        }
+
+        #[ink::test]
+        fn identical_rankings_share_an_entry() {
+            let accounts = accounts();
+            let mut contract = Ballot::new(proposals(3), config(VotingMode::Ranked));
+            let voters = vec![(accounts.bob, 2), (accounts.charlie, 1)];
+            assert_eq!(contract.give_rights_to_vote(0, voters), Ok(Vec::new()));
+            start_voting(&contract, 0);
+
+            assert_eq!(contract.vote_ranked(0, vec![2, 0]), Ok(()));
+            set_caller(accounts.bob);
+            assert_eq!(contract.vote_ranked(0, vec![2, 0]), Ok(()));
+            set_caller(accounts.charlie);
+            assert_eq!(contract.vote_ranked(0, vec![1]), Ok(()));
+            assert_eq!(contract.ranked_ballot_count.get(0), Some(2));
+            assert_eq!(contract.ranked_ballots.get((0, 0)).unwrap().weight, 3);
+
+            // an emptied ranking no longer takes part in the tally
+            assert_eq!(contract.revoke_vote(0), Ok(()));
+            assert_eq!(contract.rankings(0).len(), 1);
+            assert_eq!(contract.runoff_rounds(0), Ok(vec![vec![0, 0, 3]]));
+            assert_eq!(contract.vote_ranked(0, vec![1, 2]), Ok(()));
+            assert_eq!(contract.ranked_ballot_count.get(0), Some(3));
+        }
+
+        #[ink::test]
+        fn batch_registration_reports_rejected_entries() {
+            let accounts = accounts();
+            let mut contract = Ballot::new(proposals(2), config(VotingMode::Plurality));
+            assert_eq!(contract.give_rights_to_vote(0, vec![(accounts.bob, 1)]), Ok(Vec::new()));
+            assert_eq!(contract.delegate(0, accounts.bob, None), Ok(()));
+
+            let before = ink_env::test::recorded_events().count();
+            let voters = vec![
+                (accounts.alice, 1),
+                (accounts.charlie, 0),
+                (accounts.django, 3),
+                (accounts.django, 5),
+            ];
+            assert_eq!(
+                contract.give_rights_to_vote(0, voters),
+                Ok(vec![
+                    (accounts.alice, BallotError::AlreadyVoted),
+                    (accounts.charlie, BallotError::ZeroWeight),
+                    (accounts.django, BallotError::AlreadyHasRightToVote),
+                ])
+            );
+            // only django is registered, with the first weight
+            assert_eq!(ink_env::test::recorded_events().count(), before + 1);
+            assert_eq!(weight(&contract, 0, accounts.django), 3);
+            assert_eq!(weight(&contract, 0, accounts.charlie), 0);
+
+            set_caller(accounts.bob);
+            assert_eq!(contract.give_rights_to_vote(0, vec![(accounts.eve, 1)]), Err(BallotError::NotChairperson));
+        }
+
+        #[ink::test]
+        fn only_revealed_commitments_are_counted() {
+            let accounts = accounts();
+            let mut ballot_config = config(VotingMode::Secret);
+            ballot_config.reveal_end = 2_000_000;
+            let mut contract = Ballot::new(proposals(2), ballot_config);
+            let voters = vec![(accounts.bob, 2), (accounts.charlie, 5)];
+            assert_eq!(contract.give_rights_to_vote(0, voters), Ok(Vec::new()));
+            start_voting(&contract, 0);
+
+            // alice commits for another ballot, charlie copies bob
+            let salt = [7; 32];
+            assert_eq!(contract.commit_vote(0, vote_commitment(1, accounts.alice, 0, salt)), Ok(()));
+            set_caller(accounts.bob);
+            assert_eq!(contract.commit_vote(0, vote_commitment(0, accounts.bob, 1, salt)), Ok(()));
+            set_caller(accounts.charlie);
+            assert_eq!(contract.commit_vote(0, vote_commitment(0, accounts.bob, 1, salt)), Ok(()));
+            assert_eq!(contract.reveal_vote(0, 1, salt), Err(BallotError::WrongPhase));
+            assert_eq!(vote_counts(&contract, 0), vec![0, 0]);
+
+            end_voting(&contract, 0);
+            assert_eq!(contract.phase(0), Ok(Phase::Reveal));
+            assert_eq!(contract.reveal_vote(0, 1, salt), Err(BallotError::CommitmentMismatch));
+            set_caller(accounts.alice);
+            assert_eq!(contract.reveal_vote(0, 0, salt), Err(BallotError::CommitmentMismatch));
+            set_caller(accounts.bob);
+            assert_eq!(contract.reveal_vote(0, 1, [8; 32]), Err(BallotError::CommitmentMismatch));
+            assert_eq!(contract.reveal_vote(0, 1, salt), Ok(()));
+            assert_eq!(contract.reveal_vote(0, 1, salt), Err(BallotError::AlreadyVoted));
+
+            // unrevealed commitments stay out of the tally
+            advance_to(2_000_000);
+            assert_eq!(contract.phase(0), Ok(Phase::Tally));
+            assert_eq!(contract.commitment_counts(0), Ok((3, 1)));
+            assert_eq!(vote_counts(&contract, 0), vec![0, 2]);
+            assert_eq!(contract.winning_proposal(0), Ok(1));
+        }
+
+        #[ink::test]
+        fn changed_and_revoked_votes_carry_delegated_weight() {
+            let accounts = accounts();
+            let mut contract = Ballot::new(proposals(2), config(VotingMode::Plurality));
+            let voters = vec![(accounts.bob, 2), (accounts.charlie, 1)];
+            assert_eq!(contract.give_rights_to_vote(0, voters), Ok(Vec::new()));
+            set_caller(accounts.bob);
+            assert_eq!(contract.delegate(0, accounts.alice, None), Ok(()));
+            start_voting(&contract, 0);
+
+            set_caller(accounts.alice);
+            assert_eq!(contract.change_vote(0, 1), Err(BallotError::NotVoted));
+            assert_eq!(contract.vote(0, 0, None), Ok(()));
+            assert_eq!(contract.change_vote(0, 1), Ok(()));
+            assert_eq!(vote_counts(&contract, 0), vec![0, 3]);
+            assert_eq!(contract.turnout(0), Ok(3));
+
+            // the delegator's weight moves with the delegate only
+            set_caller(accounts.bob);
+            assert_eq!(contract.change_vote(0, 0), Err(BallotError::HasDelegated));
+            assert_eq!(contract.revoke_vote(0), Err(BallotError::HasDelegated));
+
+            set_caller(accounts.alice);
+            assert_eq!(contract.revoke_vote(0), Ok(()));
+            assert_eq!(vote_counts(&contract, 0), vec![0, 0]);
+            assert_eq!(contract.turnout(0), Ok(0));
+
+            // weight delegated in the meantime is counted on the next vote
+            set_caller(accounts.charlie);
+            assert_eq!(contract.delegate(0, accounts.alice, None), Ok(()));
+            set_caller(accounts.alice);
+            assert_eq!(contract.vote(0, 0, None), Ok(()));
+            assert_eq!(vote_counts(&contract, 0), vec![4, 0]);
+        }
+
+        #[ink::test]
+        fn undelegating_takes_weight_back_from_the_delegates_vote() {
+            let accounts = accounts();
+            let mut contract = Ballot::new(proposals(2), config(VotingMode::Plurality));
+            let voters = vec![(accounts.bob, 2), (accounts.charlie, 1)];
+            assert_eq!(contract.give_rights_to_vote(0, voters), Ok(Vec::new()));
+            start_voting(&contract, 0);
+
+            // charlie -> bob -> alice, who votes for all three
+            set_caller(accounts.bob);
+            assert_eq!(contract.undelegate(0), Err(BallotError::NotDelegated));
+            assert_eq!(contract.delegate(0, accounts.alice, None), Ok(()));
+            set_caller(accounts.charlie);
+            assert_eq!(contract.delegate(0, accounts.bob, None), Ok(()));
+            set_caller(accounts.alice);
+            assert_eq!(contract.vote(0, 0, None), Ok(()));
+            assert_eq!(vote_counts(&contract, 0), vec![4, 0]);
+
+            // bob leaves with his own weight and charlie's
+            set_caller(accounts.bob);
+            assert_eq!(contract.undelegate(0), Ok(()));
+            assert_eq!(vote_counts(&contract, 0), vec![1, 0]);
+            assert_eq!(weight(&contract, 0, accounts.alice), 1);
+            assert_eq!(contract.vote(0, 1, None), Ok(()));
+            assert_eq!(vote_counts(&contract, 0), vec![1, 3]);
+            assert_eq!(contract.undelegate(0), Err(BallotError::NotDelegated));
+
+            // charlie can go elsewhere while voting is open
+            set_caller(accounts.charlie);
+            assert_eq!(contract.undelegate(0), Ok(()));
+            assert_eq!(contract.delegate(0, accounts.alice, None), Ok(()));
+            assert_eq!(vote_counts(&contract, 0), vec![2, 2]);
+
+            end_voting(&contract, 0);
+            assert_eq!(contract.undelegate(0), Err(BallotError::WrongPhase));
+        }
+
+        #[ink::test]
+        fn abstentions_and_rejections_decide_the_result() {
+            let accounts = accounts();
+            let mut ballot_config = config(VotingMode::Plurality);
+            ballot_config.quorum = 3;
+            let mut contract = Ballot::new(proposals(2), ballot_config);
+            let voters = vec![(accounts.bob, 2), (accounts.charlie, 3)];
+            assert_eq!(contract.give_rights_to_vote(0, voters), Ok(Vec::new()));
+            start_voting(&contract, 0);
+
+            assert_eq!(contract.vote(0, 0, None), Ok(()));
+            assert_eq!(contract.result(0), Ok(BallotResult::NoQuorum));
+
+            // abstaining makes the quorum without backing anyone
+            set_caller(accounts.bob);
+            assert_eq!(contract.abstain(0, Abstention::Abstain), Ok(()));
+            assert_eq!(contract.abstain(0, Abstention::RejectAll), Err(BallotError::AlreadyVoted));
+            assert_eq!(contract.result(0), Ok(BallotResult::Passed(0)));
+
+            set_caller(accounts.charlie);
+            assert_eq!(contract.abstain(0, Abstention::RejectAll), Ok(()));
+            assert_eq!(contract.result(0), Ok(BallotResult::Rejected));
+            assert_eq!(
+                contract.tally(0),
+                Ok(Tally {
+                    vote_counts: vec![1, 0],
+                    abstentions: 2,
+                    rejections: 3,
+                    turnout: 6,
+                })
+            );
+
+            // a revoked rejection no longer counts
+            assert_eq!(contract.revoke_vote(0), Ok(()));
+            assert_eq!(contract.tally(0).unwrap().rejections, 0);
+            assert_eq!(contract.result(0), Ok(BallotResult::Passed(0)));
+        }
+
+        /// a plurality ballot whose proposal 0 pays `value`
+        /// to frank once it wins, vetoable by django
+        fn executable_ballot(contract: &mut Ballot, value: Balance) -> BallotId {
+            let accounts = accounts();
+            let mut ballot_config = config(VotingMode::Plurality);
+            ballot_config.execution_delay = 100;
+            ballot_config.guardians = vec![accounts.django];
+            let ballot_id = contract.create_ballot(proposals(2), ballot_config).unwrap();
+            let call = ProposalCall {
+                callee: accounts.frank,
+                selector: [0; 4],
+                input: Vec::new(),
+                transferred_value: value,
+            };
+            assert_eq!(contract.set_proposal_call(ballot_id, 0, Some(call)), Ok(()));
+            ballot_id
+        }
+
+        fn close_after_voting(contract: &mut Ballot, ballot_id: BallotId) {
+            end_voting(contract, ballot_id);
+            assert_eq!(contract.close(ballot_id), Ok(()));
+        }
+
+        #[ink::test]
+        fn winner_calls_wait_for_the_timelock_and_spare_reserved_funds() {
+            let mut contract = Ballot::new_registry();
+            let ballot_id = executable_ballot(&mut contract, 50);
+            start_voting(&contract, ballot_id);
+
+            // the stake of a conviction vote is owed back to alice
+            ink_env::test::set_value_transferred::<Env>(1_000);
+            assert_eq!(contract.vote_with_conviction(ballot_id, 0, 0), Ok(()));
+            ink_env::test::set_value_transferred::<Env>(0);
+            assert_eq!(contract.execute_winner(ballot_id), Err(BallotError::WrongPhase));
+
+            close_after_voting(&mut contract, ballot_id);
+            assert_eq!(contract.execute_winner(ballot_id), Err(BallotError::TimelockActive));
+
+            let closed_at = contract.ballots.get(ballot_id).unwrap().closed_at;
+            advance_to(closed_at + 100);
+            ink_env::test::set_account_balance::<Env>(ink_env::account_id::<Env>(), 1_020);
+            assert_eq!(contract.execute_winner(ballot_id), Err(BallotError::InsufficientFunds));
+            assert_eq!(contract.execution_state(ballot_id), Ok(ExecutionState::Queued));
+
+            // calls cannot be dispatched off-chain, so record one
+            let mut ballot = contract.ballots.get(ballot_id).unwrap();
+            ballot.execution = Some(true);
+            contract.ballots.insert(ballot_id, &ballot);
+            ink_env::test::set_account_balance::<Env>(ink_env::account_id::<Env>(), 1_050);
+            assert_eq!(contract.execute_winner(ballot_id), Err(BallotError::AlreadyExecuted));
+            assert_eq!(contract.execution_state(ballot_id), Ok(ExecutionState::Executed));
+        }
+
+        #[ink::test]
+        fn winners_without_a_call_execute_nothing() {
+            let mut contract = Ballot::new(proposals(2), config(VotingMode::Plurality));
+            start_voting(&contract, 0);
+            assert_eq!(contract.vote(0, 1, None), Ok(()));
+            close_after_voting(&mut contract, 0);
+            assert_eq!(contract.execute_winner(0), Err(BallotError::NoProposalCall));
+        }
+
+        #[ink::test]
+        fn guardians_can_veto_until_the_timelock_ends() {
+            let accounts = accounts();
+            let mut contract = Ballot::new_registry();
+            let vetoed = executable_ballot(&mut contract, 0);
+            let queued = executable_ballot(&mut contract, 0);
+            let unpassed = executable_ballot(&mut contract, 0);
+            start_voting(&contract, vetoed);
+            assert_eq!(contract.vote(vetoed, 0, None), Ok(()));
+            assert_eq!(contract.vote(queued, 0, None), Ok(()));
+            assert_eq!(contract.execution_state(vetoed), Ok(ExecutionState::Pending));
+
+            set_caller(accounts.django);
+            assert_eq!(contract.veto(vetoed, String::from("too early")), Err(BallotError::WrongPhase));
+            set_caller(accounts.alice);
+            for ballot_id in [vetoed, queued, unpassed] {
+                close_after_voting(&mut contract, ballot_id);
+            }
+            assert_eq!(contract.execution_state(vetoed), Ok(ExecutionState::Queued));
+            assert_eq!(contract.execution_state(unpassed), Ok(ExecutionState::NoAction));
+
+            assert_eq!(contract.veto(vetoed, String::from("not a guardian")), Err(BallotError::NotGuardian));
+            set_caller(accounts.django);
+            assert_eq!(contract.veto(unpassed, String::from("nothing passed")), Err(BallotError::NotPassed));
+            assert_eq!(contract.veto(vetoed, String::from("pays the wrong account")), Ok(()));
+            assert_eq!(contract.veto(vetoed, String::from("twice")), Err(BallotError::Vetoed));
+            assert_eq!(contract.execution_state(vetoed), Ok(ExecutionState::Vetoed));
+
+            let closed_at = contract.ballots.get(queued).unwrap().closed_at;
+            advance_to(closed_at + 100);
+            assert_eq!(contract.veto(queued, String::from("too late")), Err(BallotError::TimelockExpired));
+            assert_eq!(contract.execution_state(queued), Ok(ExecutionState::Queued));
+            assert_eq!(contract.execute_winner(vetoed), Err(BallotError::Vetoed));
+        }
+
+        #[ink::test]
+        fn each_house_counts_once_per_ballot() {
+            let accounts = accounts();
+            let mut contract = Ballot::new(proposals(2), config(VotingMode::Plurality));
+            assert_eq!(contract.count_houses(0, accounts.bob, vec![1, 257]), 2);
+            assert_eq!(contract.house_voter(0, 257), Some(accounts.bob));
+
+            // house 1 was sold to charlie after bob's vote
+            assert_eq!(contract.count_houses(0, accounts.charlie, vec![1, 2]), 1);
+            assert_eq!(contract.house_voter(0, 1), Some(accounts.bob));
+            assert_eq!(contract.house_voter(0, 2), Some(accounts.charlie));
+
+            // another ballot counts the houses afresh
+            assert_eq!(contract.count_houses(1, accounts.charlie, vec![1, 2]), 2);
+        }
+
+        #[ink::test]
+        fn delegated_weight_leaves_conviction_votes_without_a_bonus() {
+            let accounts = accounts();
+            let mut contract = Ballot::new(proposals(2), config(VotingMode::Plurality));
+            let voters = vec![(accounts.bob, 1)];
+            assert_eq!(contract.give_rights_to_vote(0, voters), Ok(Vec::new()));
+            start_voting(&contract, 0);
+
+            // only alice's own weight counts 4 times
+            set_caller(accounts.bob);
+            assert_eq!(contract.delegate(0, accounts.alice, None), Ok(()));
+            set_caller(accounts.alice);
+            assert_eq!(contract.vote_with_conviction(0, 0, 2), Ok(()));
+            assert_eq!(vote_counts(&contract, 0), vec![5, 0]);
+
+            set_caller(accounts.bob);
+            assert_eq!(contract.undelegate(0), Ok(()));
+            assert_eq!(vote_counts(&contract, 0), vec![4, 0]);
+            assert_eq!(contract.vote(0, 1, None), Ok(()));
+            assert_eq!(vote_counts(&contract, 0), vec![4, 1]);
+            assert_eq!(contract.turnout(0), Ok(5));
+        }
+
+        #[ink::test]
+        fn submitted_proposals_pay_back_their_deposit_unless_rejected() {
+            let accounts = accounts();
+            let mut ballot_config = config(VotingMode::Plurality);
+            ballot_config.proposal_end = 20;
+            ballot_config.max_proposals = 4;
+            ballot_config.proposal_deposit = 100;
+            let mut contract = Ballot::new(proposals(2), ballot_config);
+            assert_eq!(contract.give_rights_to_vote(0, vec![(accounts.bob, 1)]), Ok(Vec::new()));
+            set_caller(accounts.bob);
+            ink_env::test::set_value_transferred::<Env>(100);
+            assert_eq!(contract.submit_proposal(0, String::from("early"), [0; 32]), Err(BallotError::WrongPhase));
+
+            advance_to(10);
+            assert_eq!(contract.phase(0), Ok(Phase::Proposal));
+            assert_eq!(contract.submit_proposal(0, String::from("new roof"), [1; 32]), Ok(2));
+            assert_eq!(contract.submit_proposal(0, String::from("spam"), [2; 32]), Ok(3));
+            assert_eq!(contract.submit_proposal(0, String::from("more"), [3; 32]), Err(BallotError::TooManyProposals));
+            set_caller(accounts.charlie);
+            assert_eq!(contract.submit_proposal(0, String::from("outsider"), [4; 32]), Err(BallotError::NoRightToVote));
+            ink_env::test::set_value_transferred::<Env>(0);
+            assert_eq!(contract.reserved, 200);
+
+            set_caller(accounts.alice);
+            let bob_balance = ink_env::test::get_account_balance::<Env>(accounts.bob).unwrap();
+            assert_eq!(contract.reject_proposal(0, 3), Ok(()));
+            assert_eq!(contract.reserved, 100);
+            assert_eq!(contract.vote(0, 3, None), Err(BallotError::WrongPhase));
+
+            start_voting(&contract, 0);
+            assert_eq!(contract.vote(0, 3, None), Err(BallotError::ProposalRejected));
+            set_caller(accounts.bob);
+            assert_eq!(contract.reclaim_deposit(0, 2), Err(BallotError::WrongPhase));
+
+            end_voting(&contract, 0);
+            ink_env::test::set_account_balance::<Env>(ink_env::account_id::<Env>(), 1_000);
+            assert_eq!(contract.reclaim_deposit(0, 3), Err(BallotError::NoDeposit));
+            assert_eq!(contract.reclaim_deposit(0, 2), Ok(()));
+            assert_eq!(contract.reclaim_deposit(0, 2), Err(BallotError::NoDeposit));
+            assert_eq!(ink_env::test::get_account_balance::<Env>(accounts.bob), Ok(bob_balance + 100));
+            assert_eq!(contract.reserved, 0);
+            set_caller(accounts.alice);
+            assert_eq!(contract.reclaim_deposit(0, 2), Err(BallotError::NotProposer));
+        }
     }
 }
@@ -0,0 +1,104 @@
+//! Merkle allowlists of `(account, weight)` voters.
+//!
+//! A leaf is the Blake2x256 hash of the SCALE encoded
+//! `(account, weight)` pair. Inner nodes hash their two
+//! children in sorted order, so a proof is just the list
+//! of siblings from the leaf up to the root.
+
+use ink_env::{
+    hash::Blake2x256,
+    AccountId,
+};
+#[cfg(feature = "std")]
+use ink_prelude::vec::Vec;
+
+/// A leaf, inner node or root of the tree.
+pub type Node = [u8; 32];
+
+/// The leaf granting `account` a voting weight of `weight`.
+pub fn leaf(account: &AccountId, weight: u64) -> Node {
+    let mut output = Node::default();
+    ink_env::hash_bytes::<Blake2x256>(&scale::Encode::encode(&(account, weight)), &mut output);
+    output
+}
+
+/// Whether `proof` leads from `leaf` up to `root`.
+pub fn verify(root: &Node, leaf: Node, proof: &[Node]) -> bool {
+    proof.iter().fold(leaf, |node, sibling| hash_pair(&node, sibling)) == *root
+}
+
+fn hash_pair(a: &Node, b: &Node) -> Node {
+    let (first, second) = if a <= b { (a, b) } else { (b, a) };
+    let mut input = [0; 64];
+    input[..32].copy_from_slice(first);
+    input[32..].copy_from_slice(second);
+    let mut output = Node::default();
+    ink_env::hash_bytes::<Blake2x256>(&input, &mut output);
+    output
+}
+
+/// Hashes every pair of nodes of a level into the level above,
+/// a node left without a sibling moves up unchanged.
+#[cfg(feature = "std")]
+fn next_level(level: &[Node]) -> Vec<Node> {
+    level
+        .chunks(2)
+        .map(|pair| match pair {
+            [a, b] => hash_pair(a, b),
+            [a] => *a,
+            _ => unreachable!("chunks are never empty"),
+        })
+        .collect()
+}
+
+/// The root of the tree over `leaves`, to be committed by the
+/// chairperson with `set_voter_root`.
+#[cfg(feature = "std")]
+pub fn root(leaves: &[Node]) -> Node {
+    let mut level = leaves.to_vec();
+    if level.is_empty() {
+        return Node::default()
+    }
+    while level.len() > 1 {
+        level = next_level(&level);
+    }
+    level[0]
+}
+
+/// The proof for the leaf at `index`, to be submitted by the
+/// voter when claiming their weight.
+#[cfg(feature = "std")]
+pub fn proof(leaves: &[Node], mut index: usize) -> Vec<Node> {
+    let mut proof = Vec::new();
+    let mut level = leaves.to_vec();
+    while level.len() > 1 {
+        if let Some(sibling) = level.get(index ^ 1) {
+            proof.push(*sibling);
+        }
+        level = next_level(&level);
+        index /= 2;
+    }
+    proof
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_proof_leads_to_the_root() {
+        // odd counts leave a node without a sibling on some level
+        for count in 1..=7u8 {
+            let leaves: Vec<Node> = (0..count)
+                .map(|i| leaf(&AccountId::from([i; 32]), u64::from(i) + 1))
+                .collect();
+            let top = root(&leaves);
+            for (index, node) in leaves.iter().enumerate() {
+                assert!(verify(&top, *node, &proof(&leaves, index)));
+            }
+            // a weight that was not listed does not verify
+            let forged = leaf(&AccountId::from([0; 32]), 100);
+            assert!(!verify(&top, forged, &proof(&leaves, 0)));
+        }
+    }
+}
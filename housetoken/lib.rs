@@ -2,6 +2,12 @@
 
 use ink_lang as ink;
 
+pub use self::housetoken::{
+    HouseId,
+    Housetoken,
+    HousetokenRef,
+};
+
 #[ink::contract]
 mod housetoken {
     use ink_storage::{Mapping, traits::{SpreadAllocate, PackedLayout, SpreadLayout}};
@@ -10,7 +16,7 @@ mod housetoken {
         traits::Storage
     };
     use ink_prelude::{
-        // vec::Vec,
+        vec::Vec,
         vec,
         string::String
     };
@@ -36,6 +42,9 @@ mod housetoken {
     pub struct Housetoken {
         houses: Mapping<HouseId, House>,
         house_exists: Mapping<HouseId, bool>,
+        // the houses each account owns, so they can be
+        // listed without going through every house
+        owned_houses: Mapping<AccountId, Vec<HouseId>>,
         next_id: i32,
         admin: AccountId,
         #[storage_field]
@@ -103,7 +112,9 @@ mod housetoken {
             };
 
             self.houses.insert(id, &house);
-            self._mint_to(caller, Id::U8(id as u8)).unwrap_or_default();
+            if self._mint_to(caller, token_id(id)).is_ok() {
+                self.add_owned_house(caller, id);
+            }
             self.house_exists.insert(id, &true);
         }
 
@@ -112,6 +123,12 @@ mod housetoken {
             self.houses.get(id)
         }
 
+        /// ids of all houses owned by `owner`
+        #[ink(message)]
+        pub fn houses_of(&self, owner: AccountId) -> Vec<HouseId> {
+            self.owned_houses.get(owner).unwrap_or_default()
+        }
+
         #[ink(message)]
         pub fn change_price(&mut self, id: i32, new_price: u128) {
             let exist_house = self.house_exists.get(id).unwrap_or_default();
@@ -131,7 +148,7 @@ mod housetoken {
             let mut house = self.houses.get(id).unwrap_or_default();
             assert!(exist_house, "houde must exists");
 
-            assert!(self._owner_of(&Id::U8(id as u8)) != Some(caller), "cannot buy your own house");
+            assert!(self._owner_of(&token_id(id)) != Some(caller), "cannot buy your own house");
             // assert!(value == house.price, "not enough balance");
             let _seller = house.owner;
             let _royalty_collector = house.royalty_collector;
@@ -143,7 +160,11 @@ mod housetoken {
             self.env().transfer(_royalty_collector, royalty_payment).unwrap_or_default();
 
             // send house token to new owner
-            self._transfer_token(caller, Id::U8(id as u8), vec![]).unwrap_or_default();
+            let token_owner = self._owner_of(&token_id(id)).unwrap_or_default();
+            if self._transfer_token(caller, token_id(id), vec![]).is_ok() {
+                self.remove_owned_house(token_owner, id);
+                self.add_owned_house(caller, id);
+            }
             //change the owneership of house
             house.owner = caller;
             self.env().emit_event(BoughtHouse{house_id: id, seller: _seller, buyer: caller});
@@ -157,6 +178,24 @@ mod housetoken {
             self.next_id += 1;
             id
         }
+
+        fn add_owned_house(&mut self, owner: AccountId, id: HouseId) {
+            let mut owned = self.owned_houses.get(owner).unwrap_or_default();
+            owned.push(id);
+            self.owned_houses.insert(owner, &owned);
+        }
+
+        fn remove_owned_house(&mut self, owner: AccountId, id: HouseId) {
+            let mut owned = self.owned_houses.get(owner).unwrap_or_default();
+            owned.retain(|&house| house != id);
+            self.owned_houses.insert(owner, &owned);
+        }
+    }
+
+    /// the PSP34 token of house `id`, wide enough
+    /// for every id so no two houses share a token
+    fn token_id(id: HouseId) -> Id {
+        Id::U32(id as u32)
     }
     
 }